@@ -1,4 +1,14 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, VecDeque},
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    iter::Peekable,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use input::normalize;
 
 #[cfg(test)]
 const TEST_INPUT: &str = "
@@ -14,8 +24,8 @@ fn parse(input: &str) -> (Vec<i64>, Vec<i64>) {
     input
         .trim()
         .lines()
-        .map(|line| line.trim().split_once("   ").unwrap())
-        .map(|(l, r)| (l.parse::<i64>().unwrap(), r.parse::<i64>().unwrap()))
+        .map(|line| line.trim().split_once(char::is_whitespace).unwrap())
+        .map(|(l, r)| (l.parse::<i64>().unwrap(), r.trim().parse::<i64>().unwrap()))
         .unzip()
 }
 
@@ -28,6 +38,7 @@ fn test_parser() {
 }
 
 fn process1(input: &str) -> i64 {
+    let input = &normalize(input);
     let (mut left, mut right) = parse(input);
     left.sort();
     right.sort();
@@ -43,6 +54,7 @@ fn test_process1() {
 }
 
 fn process2(input: &str) -> i64 {
+    let input = &normalize(input);
     let (left, right) = parse(input);
     let right = right
         .into_iter()
@@ -60,16 +72,478 @@ fn test_process2() {
     assert_eq!(process2(TEST_INPUT), 31)
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 11);
+        assert_eq!(process2(&input), 31);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Metric {
+    Absolute,
+    Squared,
+    Rank,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Similarity {
+    Intersection,
+    Jaccard,
+    WeightedCount,
+}
+
+#[derive(Debug, PartialEq)]
+struct Comparison {
+    distances: Vec<([usize; 2], i64)>,
+    similarities: Vec<([usize; 2], f64)>,
+    unmatched: Vec<Vec<i64>>,
+}
+
+fn parse_columns(input: &str) -> Vec<Vec<i64>> {
+    let mut columns = Vec::<Vec<i64>>::new();
+    for line in input.trim().lines() {
+        let row = line
+            .split_whitespace()
+            .map(|n| n.parse::<i64>().unwrap())
+            .collect::<Vec<_>>();
+        if columns.is_empty() {
+            columns.resize(row.len(), vec![]);
+        }
+        assert_eq!(row.len(), columns.len(), "ragged line {line:?}");
+        for (column, n) in columns.iter_mut().zip(row) {
+            column.push(n);
+        }
+    }
+    columns
+}
+
+#[test]
+fn test_parse_columns() {
+    assert_eq!(
+        parse_columns("1 2 3\n4 5 6"),
+        vec![vec![1, 4], vec![2, 5], vec![3, 6]]
+    );
+    let (left, right) = parse(TEST_INPUT);
+    assert_eq!(parse_columns(TEST_INPUT), vec![left, right]);
+}
+
+fn counts(column: &[i64]) -> HashMap<i64, i64> {
+    column.iter().fold(HashMap::new(), |mut map, n| {
+        *map.entry(*n).or_default() += 1;
+        map
+    })
+}
+
+impl Metric {
+    fn list() -> [Metric; 3] {
+        [Metric::Absolute, Metric::Squared, Metric::Rank]
+    }
+    fn distance(&self, a: &[i64], b: &[i64]) -> i64 {
+        let mut a = a.to_vec();
+        let mut b = b.to_vec();
+        a.sort();
+        b.sort();
+        if let Metric::Rank = self {
+            let mut ranks = a.iter().chain(&b).copied().collect::<Vec<_>>();
+            ranks.sort();
+            ranks.dedup();
+            let rank = |n: &mut i64| *n = ranks.binary_search(n).unwrap() as i64;
+            a.iter_mut().for_each(rank);
+            b.iter_mut().for_each(rank);
+        }
+        a.into_iter()
+            .zip(b)
+            .map(|(a, b)| match self {
+                Metric::Absolute | Metric::Rank => (a - b).abs(),
+                Metric::Squared => (a - b).pow(2),
+            })
+            .sum()
+    }
+}
+
+impl Similarity {
+    fn list() -> [Similarity; 3] {
+        [
+            Similarity::Intersection,
+            Similarity::Jaccard,
+            Similarity::WeightedCount,
+        ]
+    }
+    fn similarity(&self, a: &[i64], b: &[i64]) -> f64 {
+        let counts_b = counts(b);
+        match self {
+            Similarity::WeightedCount => a
+                .iter()
+                .filter_map(|n| counts_b.get(n).map(|count| n * count))
+                .sum::<i64>() as f64,
+            Similarity::Intersection | Similarity::Jaccard => {
+                let counts_a = counts(a);
+                let intersection = counts_a
+                    .iter()
+                    .filter_map(|(n, count)| counts_b.get(n).map(|c| c.min(count)))
+                    .sum::<i64>();
+                if let Similarity::Intersection = self {
+                    return intersection as f64;
+                }
+                let union = (a.len() + b.len()) as i64 - intersection;
+                if union == 0 {
+                    1.
+                } else {
+                    intersection as f64 / union as f64
+                }
+            }
+        }
+    }
+}
+
+fn compare(columns: &[Vec<i64>], metric: Metric, similarity: Similarity) -> Comparison {
+    let pairs = (0..columns.len())
+        .flat_map(|i| (i + 1..columns.len()).map(move |j| [i, j]))
+        .collect::<Vec<_>>();
+    let distances = pairs
+        .iter()
+        .map(|&[i, j]| ([i, j], metric.distance(&columns[i], &columns[j])))
+        .collect();
+    let similarities = pairs
+        .iter()
+        .map(|&[i, j]| ([i, j], similarity.similarity(&columns[i], &columns[j])))
+        .collect();
+    let all_counts = columns.iter().map(|c| counts(c)).collect::<Vec<_>>();
+    let unmatched = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            let mut ids = column
+                .iter()
+                .filter(|n| {
+                    all_counts
+                        .iter()
+                        .enumerate()
+                        .all(|(j, counts)| i == j || !counts.contains_key(n))
+                })
+                .copied()
+                .collect::<Vec<_>>();
+            ids.sort();
+            ids.dedup();
+            ids
+        })
+        .collect();
+    Comparison {
+        distances,
+        similarities,
+        unmatched,
+    }
+}
+
+#[test]
+fn test_compare() {
+    let input = &normalize(TEST_INPUT);
+    let columns = parse_columns(input);
+    let comparison = compare(&columns, Metric::Absolute, Similarity::WeightedCount);
+    assert_eq!(
+        comparison,
+        Comparison {
+            distances: vec![([0, 1], process1(input))],
+            similarities: vec![([0, 1], process2(input) as f64)],
+            unmatched: vec![vec![1, 2], vec![5, 9]],
+        }
+    );
+    assert_eq!(Metric::Squared.distance(&columns[0], &columns[1]), 35);
+    assert_eq!(Metric::Rank.distance(&columns[0], &columns[1]), 8);
+    assert_eq!(
+        Similarity::Intersection.similarity(&columns[0], &columns[1]),
+        4.
+    );
+    assert_eq!(
+        Similarity::Jaccard.similarity(&columns[0], &columns[1]),
+        0.5
+    );
+}
+
+#[test]
+fn test_compare_many_columns() {
+    let columns = parse_columns("1 2 1\n2 3 7\n3 4 3");
+    let comparison = compare(&columns, Metric::Absolute, Similarity::Intersection);
+    assert_eq!(
+        comparison.distances,
+        vec![([0, 1], 3), ([0, 2], 5), ([1, 2], 4)]
+    );
+    assert_eq!(
+        comparison.similarities,
+        vec![([0, 1], 2.), ([0, 2], 2.), ([1, 2], 1.)]
+    );
+    assert_eq!(comparison.unmatched, vec![vec![], vec![4], vec![7]]);
+}
+
+const RUN_LEN: usize = 1 << 20;
+// The most runs a single merge reads at once, to stay well within open file limits.
+const FAN_IN: usize = 64;
+
+struct SpillDir {
+    path: PathBuf,
+    n_runs: usize,
+}
+
+impl SpillDir {
+    fn new() -> io::Result<Self> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "{}-{}-{}",
+            env!("CARGO_PKG_NAME"),
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path)?;
+        Ok(SpillDir { path, n_runs: 0 })
+    }
+
+    fn spill(&mut self, values: &mut Vec<i64>) -> io::Result<PathBuf> {
+        values.sort_unstable();
+        self.write_run(values.drain(..).map(Ok))
+    }
+
+    fn write_run(&mut self, values: impl Iterator<Item = io::Result<i64>>) -> io::Result<PathBuf> {
+        let path = self.path.join(format!("run{}", self.n_runs));
+        self.n_runs += 1;
+        let mut writer = BufWriter::new(File::create(&path)?);
+        for value in values {
+            writer.write_all(&value?.to_le_bytes())?;
+        }
+        writer.flush()?;
+        Ok(path)
+    }
+
+    // Merges `runs` into longer ones, `fan_in` at a time, until at most `fan_in` are left.
+    fn merge_down(&mut self, mut runs: Vec<PathBuf>, fan_in: usize) -> io::Result<Vec<PathBuf>> {
+        assert!(
+            fan_in >= 2,
+            "merging fewer than two runs at a time never finishes"
+        );
+        while runs.len() > fan_in {
+            let mut merged = vec![];
+            for group in runs.chunks(fan_in) {
+                merged.push(self.write_run(Merge::open(group)?)?);
+                for path in group {
+                    std::fs::remove_file(path)?;
+                }
+            }
+            runs = merged;
+        }
+        Ok(runs)
+    }
+}
+
+impl Drop for SpillDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+struct Run(BufReader<File>);
+
+impl Iterator for Run {
+    type Item = io::Result<i64>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut bytes = [0; 8];
+        match self.0.read_exact(&mut bytes) {
+            Ok(()) => Some(Ok(i64::from_le_bytes(bytes))),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+struct Merge {
+    runs: Vec<Run>,
+    heads: BinaryHeap<Reverse<(i64, usize)>>,
+}
+
+impl Merge {
+    fn open(paths: &[PathBuf]) -> io::Result<Self> {
+        let mut runs = paths
+            .iter()
+            .map(|path| Ok(Run(BufReader::new(File::open(path)?))))
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut heads = BinaryHeap::new();
+        for (i, run) in runs.iter_mut().enumerate() {
+            if let Some(value) = run.next() {
+                heads.push(Reverse((value?, i)));
+            }
+        }
+        Ok(Merge { runs, heads })
+    }
+}
+
+impl Iterator for Merge {
+    type Item = io::Result<i64>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((value, i)) = self.heads.pop()?;
+        match self.runs[i].next() {
+            Some(Ok(next)) => self.heads.push(Reverse((next, i))),
+            Some(Err(err)) => return Some(Err(err)),
+            None => {}
+        }
+        Some(Ok(value))
+    }
+}
+
+fn spill_runs(
+    reader: impl BufRead,
+    spill_dir: &mut SpillDir,
+    run_len: usize,
+) -> io::Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let mut left = Vec::with_capacity(run_len);
+    let mut right = Vec::with_capacity(run_len);
+    let mut left_runs = vec![];
+    let mut right_runs = vec![];
+    for line in reader.lines() {
+        let line = normalize(&line?);
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some((l, r)) = line.trim().split_once(char::is_whitespace) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected two columns in {:?}", line.trim()),
+            ));
+        };
+        let invalid = |err| io::Error::new(io::ErrorKind::InvalidData, err);
+        left.push(l.parse::<i64>().map_err(invalid)?);
+        right.push(r.trim().parse::<i64>().map_err(invalid)?);
+        if left.len() == run_len {
+            left_runs.push(spill_dir.spill(&mut left)?);
+            right_runs.push(spill_dir.spill(&mut right)?);
+        }
+    }
+    if !left.is_empty() {
+        left_runs.push(spill_dir.spill(&mut left)?);
+        right_runs.push(spill_dir.spill(&mut right)?);
+    }
+    Ok((left_runs, right_runs))
+}
+
+// The next value of a merge, without consuming it.
+fn peek(values: &mut Peekable<Merge>) -> io::Result<Option<i64>> {
+    match values.peek() {
+        Some(Ok(value)) => Ok(Some(*value)),
+        Some(Err(_)) => Err(values.next().unwrap().unwrap_err()),
+        None => Ok(None),
+    }
+}
+
+// Consumes the values equal to `value` at the head of a merge and counts them.
+fn take_equal(values: &mut Peekable<Merge>, value: i64) -> io::Result<i64> {
+    let mut count = 0;
+    while peek(values)? == Some(value) {
+        values.next();
+        count += 1;
+    }
+    Ok(count)
+}
+
+// Both parts in a single pass over both merged columns, taken one distinct value at a
+// time. Pairing for the distance only holds back the values, grouped by value, that one
+// column has more of so far than the other.
+fn process_streaming(
+    reader: impl BufRead,
+    run_len: usize,
+    fan_in: usize,
+) -> io::Result<(i64, i64)> {
+    let mut spill_dir = SpillDir::new()?;
+    let (left_runs, right_runs) = spill_runs(reader, &mut spill_dir, run_len)?;
+    let left_runs = spill_dir.merge_down(left_runs, fan_in)?;
+    let right_runs = spill_dir.merge_down(right_runs, fan_in)?;
+    let mut left = Merge::open(&left_runs)?.peekable();
+    let mut right = Merge::open(&right_runs)?.peekable();
+
+    let mut distance = 0;
+    let mut similarity = 0;
+    let mut unpaired_left = VecDeque::new();
+    let mut unpaired_right = VecDeque::new();
+    loop {
+        let value = match (peek(&mut left)?, peek(&mut right)?) {
+            (None, None) => break,
+            (Some(l), Some(r)) => l.min(r),
+            (Some(value), None) | (None, Some(value)) => value,
+        };
+        let count_left = take_equal(&mut left, value)?;
+        let count_right = take_equal(&mut right, value)?;
+        similarity += value * count_left * count_right;
+        if count_left > 0 {
+            unpaired_left.push_back((value, count_left));
+        }
+        if count_right > 0 {
+            unpaired_right.push_back((value, count_right));
+        }
+        while let (Some((l, count_l)), Some((r, count_r))) =
+            (unpaired_left.front_mut(), unpaired_right.front_mut())
+        {
+            let n = (*count_l).min(*count_r);
+            distance += n * (*l - *r).abs();
+            *count_l -= n;
+            *count_r -= n;
+            if *count_l == 0 {
+                unpaired_left.pop_front();
+            }
+            if *count_r == 0 {
+                unpaired_right.pop_front();
+            }
+        }
+    }
+    Ok((distance, similarity))
+}
+
+#[test]
+fn test_process_streaming() {
+    for run_len in [1, 2, 4, RUN_LEN] {
+        for fan_in in [2, 3, FAN_IN] {
+            for input in input::variants(TEST_INPUT) {
+                assert_eq!(
+                    process_streaming(input.as_bytes(), run_len, fan_in).unwrap(),
+                    (process1(TEST_INPUT), process2(TEST_INPUT))
+                );
+            }
+        }
+    }
+    let err = process_streaming("3 4\n12345\n".as_bytes(), 2, FAN_IN).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
         .unwrap()
         .join(concat!("data/", env!("CARGO_PKG_NAME"), ".dat"));
-    let input = std::fs::read_to_string(path).unwrap();
+    let input = std::fs::read_to_string(&path).unwrap();
     let start = std::time::Instant::now();
     let result = process1(&input);
     println!("Result part 1: {result} in {:?}", start.elapsed());
     let start = std::time::Instant::now();
     let result = process2(&input);
     println!("Result part 2: {result} in {:?}", start.elapsed());
+    let start = std::time::Instant::now();
+    let reader = BufReader::new(File::open(&path).unwrap());
+    let (result1, result2) = process_streaming(reader, RUN_LEN, FAN_IN).unwrap();
+    println!(
+        "Result out-of-core: {result1} and {result2} in {:?}",
+        start.elapsed()
+    );
+    let start = std::time::Instant::now();
+    let columns = parse_columns(&normalize(&input));
+    for (metric, similarity) in Metric::list().into_iter().zip(Similarity::list()) {
+        let comparison = compare(&columns, metric, similarity);
+        println!(
+            "{metric:?} distance: {:?}, {similarity:?} similarity: {:?}, unmatched: {:?}",
+            comparison.distances,
+            comparison.similarities,
+            comparison
+                .unmatched
+                .iter()
+                .map(Vec::len)
+                .collect::<Vec<_>>()
+        );
+    }
+    println!("Comparisons in {:?}", start.elapsed());
 }
@@ -3,6 +3,7 @@ use std::{
     path::PathBuf,
 };
 
+use input::normalize;
 use ndarray::Array2;
 
 #[cfg(test)]
@@ -17,7 +18,8 @@ const TEST_INPUT: &str = "
     10456732
 ";
 
-fn parse(input: &str) -> Array2<u32> {
+// `.` cells have no height and can't be walked on.
+fn parse(input: &str) -> Array2<Option<u32>> {
     let n_rows = input.trim().lines().count();
     let n_cols = input.trim().lines().next().unwrap().trim().chars().count();
     Array2::from_shape_vec(
@@ -25,30 +27,39 @@ fn parse(input: &str) -> Array2<u32> {
         input
             .trim()
             .lines()
-            .flat_map(|line| line.trim().chars().map(|c| c.to_digit(10).unwrap()))
+            .flat_map(|line| {
+                line.trim()
+                    .chars()
+                    .map(|c| (c != '.').then(|| c.to_digit(10).unwrap()))
+            })
             .collect(),
     )
     .unwrap()
 }
 
-fn next_indices([i, j]: [usize; 2], [n_rows, n_cols]: [usize; 2]) -> Vec<[usize; 2]> {
+fn next_indices(
+    [i, j]: [usize; 2],
+    [n_rows, n_cols]: [usize; 2],
+    diagonal: bool,
+) -> Vec<[usize; 2]> {
     let mut indices = Vec::new();
-    if i > 0 {
-        indices.push([i - 1, j]);
-    }
-    if i < n_rows - 1 {
-        indices.push([i + 1, j]);
-    }
-    if j > 0 {
-        indices.push([i, j - 1]);
-    }
-    if j < n_cols - 1 {
-        indices.push([i, j + 1]);
+    for di in -1..=1_isize {
+        for dj in -1..=1_isize {
+            if (di == 0 && dj == 0) || (!diagonal && di != 0 && dj != 0) {
+                continue;
+            }
+            let (Some(i), Some(j)) = (i.checked_add_signed(di), j.checked_add_signed(dj)) else {
+                continue;
+            };
+            if i < n_rows && j < n_cols {
+                indices.push([i, j]);
+            }
+        }
     }
     indices
 }
 
-fn process_single1(map: &Array2<u32>, source: [usize; 2]) -> usize {
+fn process_single1(map: &Array2<Option<u32>>, source: [usize; 2]) -> usize {
     let mut paths = HashSet::new();
     let shape = [map.shape()[0], map.shape()[1]];
     paths.insert(source);
@@ -56,9 +67,9 @@ fn process_single1(map: &Array2<u32>, source: [usize; 2]) -> usize {
         paths = paths
             .into_iter()
             .flat_map(|ind| {
-                next_indices(ind, shape)
+                next_indices(ind, shape, false)
                     .into_iter()
-                    .filter(|ind| map[*ind] == i)
+                    .filter(|ind| map[*ind] == Some(i))
             })
             .collect();
     }
@@ -66,11 +77,12 @@ fn process_single1(map: &Array2<u32>, source: [usize; 2]) -> usize {
 }
 
 fn process1(input: &str) -> usize {
+    let input = &normalize(input);
     let map = parse(input);
     let [n_rows, n_cols] = [map.shape()[0], map.shape()[1]];
     (0..n_rows)
         .flat_map(|i| (0..n_cols).map(move |j| [i, j]))
-        .filter(|ind| map[*ind] == 0)
+        .filter(|ind| map[*ind] == Some(0))
         .map(|source| process_single1(&map, source))
         .sum()
 }
@@ -80,7 +92,21 @@ fn test_process1() {
     assert_eq!(process1(TEST_INPUT), 36)
 }
 
-fn process_single2(map: &Array2<u32>, source: [usize; 2]) -> usize {
+#[test]
+fn test_impassable() {
+    let input = "
+        ..90..9
+        ...1.98
+        ...2..7
+        6543456
+        765.987
+        876....
+        987....
+    ";
+    assert_eq!(process1(input), 4)
+}
+
+fn process_single2(map: &Array2<Option<u32>>, source: [usize; 2]) -> usize {
     let mut paths = HashMap::new();
     let shape = [map.shape()[0], map.shape()[1]];
     paths.insert(source, 1);
@@ -88,9 +114,9 @@ fn process_single2(map: &Array2<u32>, source: [usize; 2]) -> usize {
         paths = paths
             .into_iter()
             .flat_map(|(ind, rating)| {
-                next_indices(ind, shape)
+                next_indices(ind, shape, false)
                     .into_iter()
-                    .filter(|ind| map[*ind] == i)
+                    .filter(|ind| map[*ind] == Some(i))
                     .map(move |ind| (ind, rating))
             })
             .fold(HashMap::new(), |mut acc, (ind, rating)| {
@@ -102,11 +128,12 @@ fn process_single2(map: &Array2<u32>, source: [usize; 2]) -> usize {
 }
 
 fn process2(input: &str) -> usize {
+    let input = &normalize(input);
     let map = parse(input);
     let [n_rows, n_cols] = [map.shape()[0], map.shape()[1]];
     (0..n_rows)
         .flat_map(|i| (0..n_cols).map(move |j| [i, j]))
-        .filter(|ind| map[*ind] == 0)
+        .filter(|ind| map[*ind] == Some(0))
         .map(|source| process_single2(&map, source))
         .sum()
 }
@@ -116,6 +143,142 @@ fn test_process2() {
     assert_eq!(process2(TEST_INPUT), 81)
 }
 
+#[derive(Debug, Clone)]
+struct Rules {
+    start: u32,
+    end: u32,
+    // Height differences allowed from one cell of a trail to the next.
+    steps: Vec<i64>,
+    diagonal: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            start: 0,
+            end: 9,
+            steps: vec![1],
+            diagonal: false,
+        }
+    }
+}
+
+// Every trail from a `start` cell to an `end` cell, as the cells it goes through.
+// A trail never visits a cell twice, which keeps the count finite when flat or
+// downhill steps are allowed.
+fn trails(map: &Array2<Option<u32>>, rules: &Rules) -> Vec<Vec<[usize; 2]>> {
+    fn extend(
+        map: &Array2<Option<u32>>,
+        rules: &Rules,
+        trail: &mut Vec<[usize; 2]>,
+        trails: &mut Vec<Vec<[usize; 2]>>,
+    ) {
+        let ind = *trail.last().unwrap();
+        let height = map[ind].unwrap();
+        if height == rules.end {
+            trails.push(trail.clone());
+            return;
+        }
+        let shape = [map.shape()[0], map.shape()[1]];
+        for next in next_indices(ind, shape, rules.diagonal) {
+            let Some(next_height) = map[next] else {
+                continue;
+            };
+            if rules.steps.contains(&(next_height as i64 - height as i64)) && !trail.contains(&next)
+            {
+                trail.push(next);
+                extend(map, rules, trail, trails);
+                trail.pop();
+            }
+        }
+    }
+    let mut trails = vec![];
+    for ((i, j), height) in map.indexed_iter() {
+        if *height == Some(rules.start) {
+            extend(map, rules, &mut vec![[i, j]], &mut trails);
+        }
+    }
+    trails
+}
+
+#[test]
+fn test_trails() {
+    let map = parse(TEST_INPUT);
+    let all = trails(&map, &Rules::default());
+    assert_eq!(all.len(), 81);
+    let ends = all
+        .iter()
+        .map(|trail| (trail[0], trail[9]))
+        .collect::<HashSet<_>>();
+    assert_eq!(ends.len(), 36);
+    let reversed = Rules {
+        start: 9,
+        end: 0,
+        steps: vec![-1],
+        ..Rules::default()
+    };
+    assert_eq!(trails(&map, &reversed).len(), 81);
+    let map = parse(
+        "
+        .....0.
+        ..4321.
+        ..5..2.
+        ..6543.
+        ..7..4.
+        ..8765.
+        ..9....
+        ",
+    );
+    let mut all = trails(&map, &Rules::default());
+    all.sort();
+    assert_eq!(all.len(), 3);
+    assert_eq!(
+        all[0],
+        [
+            [0, 5],
+            [1, 5],
+            [1, 4],
+            [1, 3],
+            [1, 2],
+            [2, 2],
+            [3, 2],
+            [4, 2],
+            [5, 2],
+            [6, 2]
+        ]
+    );
+    let map = parse("0.\n.1\n");
+    let diagonal = Rules {
+        end: 1,
+        diagonal: true,
+        ..Rules::default()
+    };
+    assert_eq!(trails(&map, &diagonal), [vec![[0, 0], [1, 1]]]);
+    assert!(trails(
+        &map,
+        &Rules {
+            end: 1,
+            ..Rules::default()
+        }
+    )
+    .is_empty());
+    let map = parse("024\n");
+    let jumps = Rules {
+        end: 4,
+        steps: vec![2],
+        ..Rules::default()
+    };
+    assert_eq!(trails(&map, &jumps), [vec![[0, 0], [0, 1], [0, 2]]]);
+}
+
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 36);
+        assert_eq!(process2(&input), 81);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
@@ -128,4 +291,21 @@ fn main() {
     let start = std::time::Instant::now();
     let result = process2(&input);
     println!("Result part 2: {result} in {:?}", start.elapsed());
+    // `trails <start> <end> <step,...> [diagonal]` lists the trails under other rules.
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let ["trails", start, end, steps, rest @ ..] =
+        &args.iter().map(String::as_str).collect::<Vec<_>>()[..]
+    {
+        let rules = Rules {
+            start: start.parse().unwrap(),
+            end: end.parse().unwrap(),
+            steps: steps.split(',').map(|step| step.parse().unwrap()).collect(),
+            diagonal: rest.contains(&"diagonal"),
+        };
+        let trails = trails(&parse(&normalize(&input)), &rules);
+        for trail in &trails {
+            println!("{trail:?}");
+        }
+        println!("{} trails", trails.len());
+    }
 }
@@ -1,5 +1,7 @@
 use std::{collections::HashMap, path::PathBuf};
 
+use input::normalize;
+
 #[cfg(test)]
 const TEST_INPUT: &str = "125 17";
 
@@ -12,7 +14,7 @@ fn parse(input: &str) -> Vec<u64> {
 
 fn try_split(n: u64) -> Option<[u64; 2]> {
     let n = n.to_string();
-    if n.len() % 2 == 0 {
+    if n.len().is_multiple_of(2) {
         Some([
             n[..n.len() / 2].parse().unwrap(),
             n[n.len() / 2..].parse().unwrap(),
@@ -23,6 +25,7 @@ fn try_split(n: u64) -> Option<[u64; 2]> {
 }
 
 fn process(input: &str, n: usize) -> usize {
+    let input = &normalize(input);
     let mut stones = parse(input)
         .into_iter()
         .map(|n| (n, 1))
@@ -49,6 +52,13 @@ fn test_process() {
     assert_eq!(process(TEST_INPUT, 25), 55312)
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process(&input, 25), 55312);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
@@ -3,6 +3,7 @@ use std::{
     path::PathBuf,
 };
 
+use input::normalize;
 use ndarray::Array2;
 
 #[cfg(test)]
@@ -106,6 +107,7 @@ fn test_region() {
 }
 
 fn process1(input: &str) -> usize {
+    let input = &normalize(input);
     let map = parse(input);
     let mut regions: HashMap<char, Vec<Region>> = HashMap::new();
     let [n_rows, n_cols] = [map.shape()[0], map.shape()[1]];
@@ -158,7 +160,7 @@ struct Side {
 
 impl Side {
     fn new(edges: [usize; 2]) -> Self {
-        if edges[0] % 2 == 0 {
+        if edges[0].is_multiple_of(2) {
             let updown = true;
             let ref_i = edges[0] / 2;
             let nodes = [(edges[1] - 1) / 2, (edges[1] - 1) / 2 + 1];
@@ -285,6 +287,7 @@ fn test_region_sides() {
 }
 
 fn process2(input: &str) -> usize {
+    let input = &normalize(input);
     let map = parse(input);
     let mut regions: HashMap<char, Vec<Region>> = HashMap::new();
     let [n_rows, n_cols] = [map.shape()[0], map.shape()[1]];
@@ -372,6 +375,17 @@ fn test_process2() {
     );
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT_SMALL) {
+        assert_eq!(process1(&input), 6 * 10 + 3 * 8);
+    }
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 1930);
+        assert_eq!(process2(&input), 1206);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
@@ -1,5 +1,7 @@
 use std::path::PathBuf;
 
+use input::normalize;
+
 #[cfg(test)]
 const TEST_INPUT: &str = "
     Button A: X+94, Y+34
@@ -107,8 +109,9 @@ fn parse(input: &str) -> impl Iterator<Item = Game> + '_ {
 }
 
 fn process1(input: &str) -> i64 {
+    let input = &normalize(input);
     parse(input)
-        .filter_map(|game| game.solve().map(|r| (r.x * 3 + r.y)))
+        .filter_map(|game| game.solve().map(|r| r.x * 3 + r.y))
         .sum()
 }
 
@@ -118,15 +121,23 @@ fn test_process1() {
 }
 
 fn process2(input: &str) -> i64 {
+    let input = &normalize(input);
     parse(input)
         .filter_map(|game| {
             game.inc_prize(10000000000000)
                 .solve()
-                .map(|r| (r.x * 3 + r.y))
+                .map(|r| r.x * 3 + r.y)
         })
         .sum()
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 480);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
@@ -1,5 +1,7 @@
 use std::path::PathBuf;
 
+use input::normalize;
+
 #[cfg(test)]
 const TEST_INPUT: &str = "
     p=0,4 v=3,-3
@@ -38,6 +40,7 @@ fn test_mod() {
 }
 
 fn process1(input: &str, nx: i64, ny: i64) -> usize {
+    let input = &normalize(input);
     let quadrant = parse(input)
         .map(move |(pos, vel)| {
             [
@@ -61,6 +64,7 @@ fn test_process1() {
 }
 
 fn process2(input: &str, nx: i64, ny: i64, nt: i64) -> Vec<Vec<char>> {
+    let input = &normalize(input);
     parse(input)
         .map(move |(pos, vel)| {
             [
@@ -91,6 +95,13 @@ fn test_process2() {
     }
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input, 11, 7), 12);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
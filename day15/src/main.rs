@@ -3,6 +3,7 @@ use std::{
     path::PathBuf,
 };
 
+use input::normalize;
 use nalgebra::Vector2;
 
 #[cfg(test)]
@@ -135,6 +136,7 @@ fn parse1(input: &str) -> (Model1, Vec<Move>) {
 }
 
 fn process1(input: &str) -> i64 {
+    let input = &normalize(input);
     let (mut model, moves) = parse1(input);
     for m in moves {
         model.move_robot(m);
@@ -278,6 +280,7 @@ fn parse2(input: &str) -> (Model2, Vec<Move>) {
 }
 
 fn process2(input: &str) -> i64 {
+    let input = &normalize(input);
     let (mut model, moves) = parse2(input);
     for m in moves {
         model.move_robot(m);
@@ -290,6 +293,14 @@ fn test_process2() {
     assert_eq!(process2(TEST_INPUT), 9021)
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 10092);
+        assert_eq!(process2(&input), 9021);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
@@ -5,6 +5,7 @@ use std::{
     path::PathBuf,
 };
 
+use input::normalize;
 use ndarray::Array2;
 
 #[cfg(test)]
@@ -101,6 +102,7 @@ impl Ord for State {
 }
 
 fn process1(input: &str) -> usize {
+    let input = &normalize(input);
     let maze = parse(input);
     let [nrows, ncols] = [maze.shape()[0], maze.shape()[1]];
     let source = Node {
@@ -160,6 +162,7 @@ fn test_process1() {
 }
 
 fn process2(input: &str) -> usize {
+    let input = &normalize(input);
     let maze = parse(input);
     let [nrows, ncols] = [maze.shape()[0], maze.shape()[1]];
     let source = Node {
@@ -255,6 +258,14 @@ fn test_process2() {
     assert_eq!(process2(TEST_INPUT), 45)
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 7036);
+        assert_eq!(process2(&input), 45);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
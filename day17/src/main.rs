@@ -1,5 +1,7 @@
 use std::path::PathBuf;
 
+use input::normalize;
+
 #[cfg(test)]
 const TEST_INPUT_1: &str = "
     Register A: 729
@@ -177,6 +179,7 @@ fn parse(input: &str) -> Program {
 }
 
 fn process1(input: &str) -> String {
+    let input = &normalize(input);
     let mut program = parse(input);
     program.solve();
     program.out_str()
@@ -188,6 +191,7 @@ fn test_process1() {
 }
 
 fn process2(input: &str) -> u64 {
+    let input = &normalize(input);
     let mut program = parse(input);
     let program_len = program.program_digits.len();
     let mut base_8 = vec![];
@@ -209,6 +213,13 @@ fn process2(input: &str) -> u64 {
     value
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT_1) {
+        assert_eq!(process1(&input), "4,6,3,5,6,3,5,2,1,0");
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
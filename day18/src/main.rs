@@ -4,6 +4,8 @@ use std::{
     path::PathBuf,
 };
 
+use input::normalize;
+
 #[cfg(test)]
 const TEST_INPUT: &str = "
 5,4
@@ -41,6 +43,7 @@ fn parse(input: &str) -> impl Iterator<Item = [i64; 2]> + '_ {
 }
 
 fn process1(input: &str, n_max: i64, n_bytes: usize) -> usize {
+    let input = &normalize(input);
     let corrupted = parse(input).take(n_bytes).collect::<HashSet<_>>();
     let target = [n_max - 1; 2];
     let mut queue = BinaryHeap::new();
@@ -105,6 +108,7 @@ fn has_path(corrupted: HashSet<[i64; 2]>, n_max: i64) -> bool {
 }
 
 fn process2(input: &str, n_max: i64) -> [i64; 2] {
+    let input = &normalize(input);
     let corrupted_all = parse(input).collect::<Vec<_>>();
     let mut n_b = 0;
     let mut n_u = corrupted_all.len();
@@ -130,6 +134,14 @@ fn test_process2() {
     assert_eq!(process2(TEST_INPUT, 7), [6, 1])
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input, 7, 12), 22);
+        assert_eq!(process2(&input, 7), [6, 1]);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
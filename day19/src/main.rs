@@ -1,5 +1,7 @@
 use std::{collections::HashMap, path::PathBuf};
 
+use input::normalize;
+
 #[cfg(test)]
 const TEST_INPUT: &str = "
 r, wr, b, g, bwu, rb, gb, br
@@ -46,6 +48,7 @@ fn is_desing_doable<'a>(
 }
 
 fn process1(input: &str) -> usize {
+    let input = &normalize(input);
     let (towels, design) = parse(input);
     let mut memo = HashMap::new();
     design
@@ -81,6 +84,7 @@ fn count_patterns<'a>(
 }
 
 fn process2(input: &str) -> usize {
+    let input = &normalize(input);
     let (towels, design) = parse(input);
     let mut memo = HashMap::new();
     design
@@ -94,6 +98,14 @@ fn test_process2() {
     assert_eq!(process2(TEST_INPUT), 16)
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 6);
+        assert_eq!(process2(&input), 16);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
@@ -1,5 +1,6 @@
 use std::path::PathBuf;
 
+use input::normalize;
 use itertools::Itertools;
 
 #[cfg(test)]
@@ -39,23 +40,232 @@ fn test_parser() {
     );
 }
 
-fn is_safe(diffs: impl Iterator<Item = i64>) -> bool {
-    let mut count_up = 0;
-    let mut count_down = 0;
-    for diff in diffs {
-        match diff {
-            ..-3 | 0 | 4.. => return false,
-            ..0 => count_down += 1,
-            1.. => count_up += 1,
+#[derive(Debug, Clone, Copy)]
+struct Policy {
+    min_step: i64,
+    max_step: i64,
+    // Flat steps are exempt from the step bounds when allowed; allowing them turns
+    // strict monotonicity into non-strict monotonicity.
+    allow_plateaus: bool,
+    monotonic: bool,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            min_step: 1,
+            max_step: 3,
+            allow_plateaus: false,
+            monotonic: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Rule {
+    Plateau,
+    StepTooSmall,
+    StepTooLarge,
+    DirectionChange,
+}
+
+impl Rule {
+    fn name(&self) -> &'static str {
+        match self {
+            Rule::Plateau => "plateau",
+            Rule::StepTooSmall => "step_too_small",
+            Rule::StepTooLarge => "step_too_large",
+            Rule::DirectionChange => "direction_change",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Violation {
+    pair: usize,
+    levels: [i64; 2],
+    rule: Rule,
+}
+
+impl Policy {
+    fn check_step(&self, a: i64, b: i64, up: Option<bool>) -> Option<Rule> {
+        let diff = b - a;
+        if diff == 0 {
+            return (!self.allow_plateaus).then_some(Rule::Plateau);
+        }
+        if self.monotonic && up.is_some_and(|up| up != (diff > 0)) {
+            return Some(Rule::DirectionChange);
+        }
+        if diff.abs() < self.min_step {
+            Some(Rule::StepTooSmall)
+        } else if diff.abs() > self.max_step {
+            Some(Rule::StepTooLarge)
+        } else {
+            None
+        }
+    }
+
+    fn violations(&self, record: &[i64]) -> Vec<Violation> {
+        let mut up = None;
+        record
+            .iter()
+            .tuple_windows()
+            .enumerate()
+            .filter_map(|(pair, (&a, &b))| {
+                let rule = self.check_step(a, b, up);
+                if b != a {
+                    up = up.or(Some(b > a));
+                }
+                rule.map(|rule| Violation {
+                    pair,
+                    levels: [a, b],
+                    rule,
+                })
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_violations() {
+    let policy = Policy::default();
+    let violations = parse(TEST_INPUT)
+        .iter()
+        .map(|record| policy.violations(record))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        violations,
+        vec![
+            vec![],
+            vec![Violation {
+                pair: 1,
+                levels: [2, 7],
+                rule: Rule::StepTooLarge
+            }],
+            vec![Violation {
+                pair: 2,
+                levels: [6, 2],
+                rule: Rule::StepTooLarge
+            }],
+            vec![Violation {
+                pair: 1,
+                levels: [3, 2],
+                rule: Rule::DirectionChange
+            }],
+            vec![Violation {
+                pair: 2,
+                levels: [4, 4],
+                rule: Rule::Plateau
+            }],
+            vec![],
+        ]
+    );
+    let policy = Policy {
+        max_step: 5,
+        allow_plateaus: true,
+        ..Policy::default()
+    };
+    assert!(policy.violations(&[1, 2, 7, 7, 9]).is_empty());
+    let policy = Policy {
+        min_step: 2,
+        monotonic: false,
+        ..Policy::default()
+    };
+    assert!(policy.violations(&[1, 3, 1, 4]).is_empty());
+    assert_eq!(policy.violations(&[1, 2])[0].rule, Rule::StepTooSmall);
+}
+
+struct Diagnostic {
+    report: usize,
+    levels: Vec<i64>,
+    violations: Vec<Violation>,
+}
+
+fn diagnose(data: &[Vec<i64>], policy: &Policy) -> Vec<Diagnostic> {
+    data.iter()
+        .enumerate()
+        .map(|(report, levels)| Diagnostic {
+            report,
+            levels: levels.clone(),
+            violations: policy.violations(levels),
+        })
+        .collect()
+}
+
+fn to_csv(diagnostics: &[Diagnostic]) -> String {
+    let mut csv = String::from("report,levels,safe,pair,first,second,rule\n");
+    for diagnostic in diagnostics {
+        let prefix = format!(
+            "{},{},{}",
+            diagnostic.report,
+            diagnostic.levels.iter().join(" "),
+            diagnostic.violations.is_empty()
+        );
+        if diagnostic.violations.is_empty() {
+            csv += &format!("{prefix},,,,\n");
+        }
+        for violation in diagnostic.violations.iter() {
+            let [first, second] = violation.levels;
+            csv += &format!(
+                "{prefix},{},{first},{second},{}\n",
+                violation.pair,
+                violation.rule.name()
+            );
         }
     }
-    count_down.min(count_up) == 0
+    csv
+}
+
+fn to_json(diagnostics: &[Diagnostic]) -> String {
+    let reports = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let violations = diagnostic
+                .violations
+                .iter()
+                .map(|violation| {
+                    format!(
+                        r#"{{"pair":{},"levels":[{},{}],"rule":"{}"}}"#,
+                        violation.pair,
+                        violation.levels[0],
+                        violation.levels[1],
+                        violation.rule.name()
+                    )
+                })
+                .join(",");
+            format!(
+                r#"{{"report":{},"levels":[{}],"safe":{},"violations":[{violations}]}}"#,
+                diagnostic.report,
+                diagnostic.levels.iter().join(","),
+                diagnostic.violations.is_empty()
+            )
+        })
+        .join(",");
+    format!("[{reports}]")
+}
+
+#[test]
+fn test_export() {
+    let diagnostics = diagnose(&parse("7 6 4 2 1\n1 3 2 4 5"), &Policy::default());
+    assert_eq!(
+        to_csv(&diagnostics),
+        "report,levels,safe,pair,first,second,rule\n\
+         0,7 6 4 2 1,true,,,,\n\
+         1,1 3 2 4 5,false,1,3,2,direction_change\n"
+    );
+    assert_eq!(
+        to_json(&diagnostics),
+        r#"[{"report":0,"levels":[7,6,4,2,1],"safe":true,"violations":[]},"#.to_string()
+            + r#"{"report":1,"levels":[1,3,2,4,5],"safe":false,"violations":"#
+            + r#"[{"pair":1,"levels":[3,2],"rule":"direction_change"}]}]"#
+    );
 }
 
 fn process1(input: &str) -> usize {
+    let input = &normalize(input);
     let data = parse(input);
     data.into_iter()
-        .filter(|record| is_safe(record.iter().tuple_windows().map(|(i, j)| j - i)))
+        .filter(|record| Policy::default().violations(record).is_empty())
         .count()
 }
 
@@ -64,28 +274,101 @@ fn test_process1() {
     assert_eq!(process1(TEST_INPUT), 2)
 }
 
-fn is_almost_safe(record: &[i64]) -> bool {
-    if is_safe(record.iter().tuple_windows().map(|(i, j)| j - i)) {
-        return true;
-    };
-    for i in 0..record.len() {
-        if is_safe(
-            (0..record.len())
-                .filter(|j| j != &i)
-                .map(|i| record[i])
-                .tuple_windows()
-                .map(|(i, j)| j - i),
-        ) {
-            return true;
+fn safe_removals(record: &[i64], k: usize, policy: &Policy) -> Option<Vec<usize>> {
+    let n = record.len();
+    if n == 0 {
+        return Some(vec![]);
+    }
+    let mut best: Option<Vec<usize>> = None;
+    for up in [true, false] {
+        // removals[i]: fewest removals among record[..i] so that record[i] is kept and the
+        // kept levels up to it are safe, with the previous kept level in parent[i].
+        let mut removals = vec![usize::MAX; n];
+        let mut parent = vec![None; n];
+        for i in 0..n {
+            if i <= k {
+                removals[i] = i;
+            }
+            for j in i.saturating_sub(k + 1)..i {
+                if removals[j] == usize::MAX
+                    || policy.check_step(record[j], record[i], Some(up)).is_some()
+                {
+                    continue;
+                }
+                let count = removals[j] + i - j - 1;
+                if count < removals[i] {
+                    removals[i] = count;
+                    parent[i] = Some(j);
+                }
+            }
+        }
+        let Some((last, count)) = (n.saturating_sub(k + 1)..n)
+            .filter(|&i| removals[i] != usize::MAX)
+            .map(|i| (i, removals[i] + n - 1 - i))
+            .min_by_key(|&(_, count)| count)
+        else {
+            continue;
+        };
+        if count > k || best.as_ref().is_some_and(|best| best.len() <= count) {
+            continue;
         }
+        let mut kept = vec![false; n];
+        let mut current = Some(last);
+        while let Some(i) = current {
+            kept[i] = true;
+            current = parent[i];
+        }
+        best = Some((0..n).filter(|&i| !kept[i]).collect());
+    }
+    best
+}
+
+#[test]
+fn test_safe_removals() {
+    let policy = Policy::default();
+    let data = parse(TEST_INPUT);
+    let expected = [
+        Some(vec![]),
+        None,
+        None,
+        Some(vec![2]),
+        Some(vec![3]),
+        Some(vec![]),
+    ];
+    for (record, expected) in data.iter().zip(expected) {
+        assert_eq!(safe_removals(record, 1, &policy), expected);
+        assert_eq!(
+            safe_removals(record, 0, &policy).is_some(),
+            policy.violations(record).is_empty()
+        );
     }
-    false
+    assert_eq!(
+        safe_removals(&[1, 2, 7, 8, 9], 2, &policy),
+        Some(vec![0, 1])
+    );
+    assert_eq!(
+        safe_removals(&[9, 7, 6, 2, 1], 2, &policy),
+        Some(vec![3, 4])
+    );
+    assert_eq!(
+        safe_removals(&[1, 9, 2, 9, 3, 9, 4], 3, &policy),
+        Some(vec![1, 3, 5])
+    );
+    assert_eq!(safe_removals(&[1, 9, 2, 9, 3, 9, 4], 2, &policy), None);
+    assert_eq!(safe_removals(&[5, 1, 2, 3], 1, &policy), Some(vec![0]));
+    assert_eq!(safe_removals(&[3], 0, &policy), Some(vec![]));
+    assert_eq!(safe_removals(&[1, 2, 3], 2, &policy), Some(vec![]));
+    assert_eq!(safe_removals(&[1, 2, 3, 4], 3, &policy), Some(vec![]));
+    assert_eq!(safe_removals(&[1, 9, 2], 2, &policy), Some(vec![1]));
+    assert_eq!(safe_removals(&[5, 5], 1, &policy), Some(vec![1]));
+    assert_eq!(safe_removals(&[], 1, &policy), Some(vec![]));
 }
 
 fn process2(input: &str) -> usize {
+    let input = &normalize(input);
     let data = parse(input);
     data.into_iter()
-        .filter(|record| is_almost_safe(record))
+        .filter(|record| safe_removals(record, 1, &Policy::default()).is_some())
         .count()
 }
 
@@ -94,16 +377,53 @@ fn test_process2() {
     assert_eq!(process2(TEST_INPUT), 4)
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 2);
+        assert_eq!(process2(&input), 4);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
         .unwrap()
         .join(concat!("data/", env!("CARGO_PKG_NAME"), ".dat"));
     let input = std::fs::read_to_string(path).unwrap();
+    // `csv` and `json` only print the per-record report, so it can be redirected.
+    match std::env::args().nth(1).as_deref() {
+        Some("csv") => {
+            print!(
+                "{}",
+                to_csv(&diagnose(&parse(&normalize(&input)), &Policy::default()))
+            );
+            return;
+        }
+        Some("json") => {
+            println!(
+                "{}",
+                to_json(&diagnose(&parse(&normalize(&input)), &Policy::default()))
+            );
+            return;
+        }
+        _ => {}
+    }
     let start = std::time::Instant::now();
     let result = process1(&input);
     println!("Result part 1: {result} in {:?}", start.elapsed());
     let start = std::time::Instant::now();
     let result = process2(&input);
     println!("Result part 2: {result} in {:?}", start.elapsed());
+    for k in 2..=3 {
+        let start = std::time::Instant::now();
+        let result = parse(&normalize(&input))
+            .into_iter()
+            .filter(|record| safe_removals(record, k, &Policy::default()).is_some())
+            .count();
+        println!(
+            "Result with {k} removals: {result} in {:?}",
+            start.elapsed()
+        );
+    }
 }
@@ -1,5 +1,6 @@
 use std::path::PathBuf;
 
+use input::normalize;
 use ndarray::Array2;
 
 #[cfg(test)]
@@ -45,6 +46,7 @@ fn parse(input: &str) -> (Array2<bool>, [usize; 2], [usize; 2]) {
 }
 
 fn process1(input: &str, threshold: usize) -> usize {
+    let input = &normalize(input);
     let (track, source, target) = parse(input);
     let mut path = vec![source];
     let mut current = source;
@@ -76,6 +78,7 @@ fn test_process1() {
 }
 
 fn process2(input: &str, threshold: usize) -> usize {
+    let input = &normalize(input);
     let (track, source, target) = parse(input);
     let mut path = vec![source];
     let mut current = source;
@@ -111,6 +114,14 @@ fn test_process2() {
     assert_eq!(process2(TEST_INPUT, 72), 29)
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input, 12), 8);
+        assert_eq!(process2(&input, 72), 29);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
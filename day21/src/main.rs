@@ -1,5 +1,7 @@
 use std::{cmp::Ordering::*, collections::HashMap, path::PathBuf};
 
+use input::normalize;
+
 #[cfg(test)]
 const TEST_INPUT: &str = "
     029A
@@ -101,14 +103,14 @@ fn get_all_paths<const HOLE_Y: i32>(start: [i32; 2], end: [i32; 2]) -> Vec<Vec<D
             if !(j_start == HOLE_Y && i_end == 0) {
                 paths.push({
                     let mut path = vec![Left; n_left];
-                    path.extend(std::iter::repeat(Down).take(n_down));
+                    path.extend(std::iter::repeat_n(Down, n_down));
                     path
                 });
             }
             if !(i_start == 0 && j_end == HOLE_Y) {
                 paths.push({
                     let mut path = vec![Down; n_down];
-                    path.extend(std::iter::repeat(Left).take(n_left));
+                    path.extend(std::iter::repeat_n(Left, n_left));
                     path
                 });
             }
@@ -119,13 +121,13 @@ fn get_all_paths<const HOLE_Y: i32>(start: [i32; 2], end: [i32; 2]) -> Vec<Vec<D
             if !(j_start == HOLE_Y && i_end == 0) {
                 paths.push({
                     let mut path = vec![Left; n_left];
-                    path.extend(std::iter::repeat(Up).take(n_up));
+                    path.extend(std::iter::repeat_n(Up, n_up));
                     path
                 });
             }
             paths.push({
                 let mut path = vec![Up; n_up];
-                path.extend(std::iter::repeat(Left).take(n_left));
+                path.extend(std::iter::repeat_n(Left, n_left));
                 path
             });
         }
@@ -134,13 +136,13 @@ fn get_all_paths<const HOLE_Y: i32>(start: [i32; 2], end: [i32; 2]) -> Vec<Vec<D
             let n_down = (j_start - j_end) as usize;
             paths.push({
                 let mut path = vec![Right; n_right];
-                path.extend(std::iter::repeat(Down).take(n_down));
+                path.extend(std::iter::repeat_n(Down, n_down));
                 path
             });
             if !(i_start == 0 && j_end == HOLE_Y) {
                 paths.push({
                     let mut path = vec![Down; n_down];
-                    path.extend(std::iter::repeat(Right).take(n_right));
+                    path.extend(std::iter::repeat_n(Right, n_right));
                     path
                 });
             }
@@ -150,13 +152,13 @@ fn get_all_paths<const HOLE_Y: i32>(start: [i32; 2], end: [i32; 2]) -> Vec<Vec<D
             let n_up = (j_end - j_start) as usize;
             paths.push({
                 let mut path = vec![Right; n_right];
-                path.extend(std::iter::repeat(Up).take(n_up));
+                path.extend(std::iter::repeat_n(Up, n_up));
                 path
             });
             if !(i_start == 0 && j_end == HOLE_Y) {
                 paths.push({
                     let mut path = vec![Up; n_up];
-                    path.extend(std::iter::repeat(Right).take(n_right));
+                    path.extend(std::iter::repeat_n(Right, n_right));
                     path
                 });
             }
@@ -257,6 +259,7 @@ fn test_path_cost() {
 }
 
 fn process(input: &str, n_rooms: usize) -> usize {
+    let input = &normalize(input);
     let mut memo = HashMap::new();
     input
         .trim()
@@ -287,6 +290,13 @@ fn test_process1() {
     assert_eq!(process(TEST_INPUT, 2), 126384)
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process(&input, 2), 126384);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
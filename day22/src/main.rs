@@ -3,6 +3,8 @@ use std::{
     path::PathBuf,
 };
 
+use input::normalize;
+
 #[cfg(test)]
 const TEST_INPUT1: &str = "
 1
@@ -43,6 +45,7 @@ fn test_apply_n() {
 }
 
 fn process1(input: &str) -> u64 {
+    let input = &normalize(input);
     parse(input).map(|secret| apply_n(secret, 2000)).sum()
 }
 
@@ -89,6 +92,7 @@ fn test_get_sequence_map() {
 }
 
 fn process2(input: &str) -> i64 {
+    let input = &normalize(input);
     let mut map = HashMap::<VecDeque<i64>, i64>::default();
     for secret in parse(input) {
         for (seq, price) in get_sequence_map(secret, 2000) {
@@ -103,6 +107,16 @@ fn test_process2() {
     assert_eq!(process2(TEST_INPUT2), 23)
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT1) {
+        assert_eq!(process1(&input), 37327623);
+    }
+    for input in input::variants(TEST_INPUT2) {
+        assert_eq!(process2(&input), 23);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
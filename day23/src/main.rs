@@ -3,6 +3,8 @@ use std::{
     path::PathBuf,
 };
 
+use input::normalize;
+
 #[cfg(test)]
 const TEST_INPUT: &str = "
 kh-tc
@@ -52,6 +54,7 @@ fn parse(input: &str) -> BTreeMap<&str, BTreeSet<&str>> {
 }
 
 fn process1(input: &str) -> usize {
+    let input = &normalize(input);
     let adjs = parse(input);
     let mut groups = vec![];
     for (k1, set2) in adjs.iter() {
@@ -75,6 +78,7 @@ fn test_process1() {
 }
 
 fn process2(input: &str) -> String {
+    let input = &normalize(input);
     let adjs = parse(input);
     let mut largest = BTreeSet::default();
     'outer: for (k1, nexts) in adjs.iter() {
@@ -100,6 +104,14 @@ fn test_process2() {
     assert_eq!(process2(TEST_INPUT), "co,de,ka,ta")
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 7);
+        assert_eq!(process2(&input), "co,de,ka,ta");
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
@@ -3,6 +3,8 @@ use std::{
     path::PathBuf,
 };
 
+use input::normalize;
+
 #[cfg(test)]
 const TEST_INPUT: &str = "
     x00: 1
@@ -165,6 +167,7 @@ impl<'a> Problem<'a> {
 }
 
 fn process1(input: &str) -> usize {
+    let input = &normalize(input);
     Problem::parse(input).solve_p1()
 }
 
@@ -174,9 +177,17 @@ fn test_process1() {
 }
 
 fn process2(input: &str) -> String {
+    let input = &normalize(input);
     Problem::parse(input).solve_p2()
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 2024);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
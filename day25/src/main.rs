@@ -1,5 +1,7 @@
 use std::path::PathBuf;
 
+use input::normalize;
+
 #[cfg(test)]
 const TEST_INPUT: &str = "
     #####
@@ -76,6 +78,7 @@ fn test_parse() {
 }
 
 fn process1(input: &str) -> usize {
+    let input = &normalize(input);
     let (locks, keys) = parse(input);
     locks
         .iter()
@@ -91,6 +94,13 @@ fn test_process1() {
     assert_eq!(process1(TEST_INPUT), 3)
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 3);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
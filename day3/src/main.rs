@@ -1,24 +1,460 @@
-use regex::Regex;
-use std::path::PathBuf;
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    fs::File,
+    io::{self, Read},
+    ops::{Range, RangeInclusive},
+    path::PathBuf,
+};
+
+use input::normalize;
 
 #[cfg(test)]
 const TEST_INPUT: &str =
     "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
 
-fn process1(input: &str) -> u64 {
-    Regex::new(r"mul\(\d{1,3},\d{1,3}\)")
-        .unwrap()
-        .find_iter(input)
-        .map(|mat| {
-            let (num1_str, num2_str) = mat
-                .as_str()
-                .trim_start_matches("mul(")
-                .trim_end_matches(")")
-                .split_once(',')
-                .unwrap();
-            num1_str.parse::<u64>().unwrap() * num2_str.parse::<u64>().unwrap()
+const CHUNK_LEN: usize = 1 << 16;
+
+#[derive(Debug, PartialEq)]
+struct Instruction {
+    opcode: usize,
+    args: Vec<u64>,
+    span: Range<usize>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Rejection {
+    Unexpected(u8),
+    TooManyDigits,
+    WrongArity(usize),
+    Truncated,
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rejection::Unexpected(b) => write!(f, "unexpected {:?}", *b as char),
+            Rejection::TooManyDigits => write!(f, "too many digits"),
+            Rejection::WrongArity(n) => write!(f, "wrong number of arguments ({n})"),
+            Rejection::Truncated => write!(f, "truncated by end of input"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct NearMiss {
+    span: Range<usize>,
+    rejection: Rejection,
+}
+
+enum Scan {
+    Partial,
+    Complete(Vec<u64>),
+    // The rejection is only reported once the opcode name has been matched and not
+    // continued as a longer word.
+    Invalid(Option<Rejection>),
+}
+
+#[derive(Debug, Default)]
+struct Machine {
+    disabled: bool,
+    total: i64,
+}
+
+struct Opcode {
+    name: &'static str,
+    arity: RangeInclusive<usize>,
+    max_digits: usize,
+    apply: fn(&mut Machine, &[u64]),
+}
+
+impl Opcode {
+    fn scan(&self, bytes: &[u8]) -> Scan {
+        let head_len = self.name.len() + 1;
+        let head = self.name.bytes().chain([b'(']);
+        if let Some(i) = head.zip(bytes).position(|(a, b)| a != *b) {
+            // A name running on into a longer word, like `mule`, is just another word.
+            let near_miss = i == head_len - 1 && !bytes[i].is_ascii_alphanumeric();
+            return Scan::Invalid(near_miss.then_some(Rejection::Unexpected(bytes[i])));
+        }
+        if bytes.len() <= head_len {
+            return Scan::Partial;
+        }
+        let mut args = vec![];
+        let mut digits = 0;
+        for &b in &bytes[head_len..] {
+            match b {
+                b'0'..=b'9' if digits == self.max_digits => {
+                    return Scan::Invalid(Some(Rejection::TooManyDigits))
+                }
+                b'0'..=b'9' => {
+                    if digits == 0 {
+                        args.push(0);
+                    }
+                    let arg = args.last_mut().unwrap();
+                    *arg = *arg * 10 + (b - b'0') as u64;
+                    digits += 1;
+                }
+                b',' if digits > 0 && args.len() < *self.arity.end() => digits = 0,
+                b',' if digits > 0 => {
+                    return Scan::Invalid(Some(Rejection::WrongArity(args.len() + 1)))
+                }
+                b')' if (digits > 0 || args.is_empty()) && self.arity.contains(&args.len()) => {
+                    return Scan::Complete(args)
+                }
+                b')' if digits > 0 || args.is_empty() => {
+                    return Scan::Invalid(Some(Rejection::WrongArity(args.len())))
+                }
+                _ => return Scan::Invalid(Some(Rejection::Unexpected(b))),
+            }
+        }
+        Scan::Partial
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    opcodes: Vec<Opcode>,
+}
+
+impl Registry {
+    fn register(
+        mut self,
+        name: &'static str,
+        arity: RangeInclusive<usize>,
+        max_digits: usize,
+        apply: fn(&mut Machine, &[u64]),
+    ) -> Self {
+        self.opcodes.push(Opcode {
+            name,
+            arity,
+            max_digits,
+            apply,
+        });
+        self
+    }
+
+    fn part1() -> Self {
+        Registry::default().register("mul", 2..=2, 3, |machine, args| {
+            machine.total += (args[0] * args[1]) as i64
+        })
+    }
+
+    fn part2() -> Self {
+        Registry::default()
+            .register("mul", 2..=2, 3, |machine, args| {
+                if !machine.disabled {
+                    machine.total += (args[0] * args[1]) as i64
+                }
+            })
+            .register("do", 0..=0, 0, |machine, _| machine.disabled = false)
+            .register("don't", 0..=0, 0, |machine, _| machine.disabled = true)
+    }
+
+    fn scan(&self, bytes: &[u8]) -> Result<Option<(usize, Vec<u64>)>, Option<Rejection>> {
+        let mut partial = false;
+        let mut rejection = None;
+        let mut matched_len = 0;
+        for (opcode, op) in self.opcodes.iter().enumerate() {
+            match op.scan(bytes) {
+                Scan::Complete(args) => return Ok(Some((opcode, args))),
+                Scan::Partial => partial = true,
+                Scan::Invalid(Some(r)) if op.name.len() >= matched_len => {
+                    matched_len = op.name.len();
+                    rejection = Some(r);
+                }
+                Scan::Invalid(_) => {}
+            }
+        }
+        if partial {
+            Ok(None)
+        } else {
+            Err(rejection)
+        }
+    }
+
+    // Whether `bytes` start with some opcode's `name(`.
+    fn heads_prefix_of(&self, bytes: &[u8]) -> bool {
+        self.opcodes.iter().any(|op| {
+            bytes.starts_with(op.name.as_bytes()) && bytes.get(op.name.len()) == Some(&b'(')
+        })
+    }
+
+    fn evaluate(
+        &self,
+        instructions: impl Iterator<Item = io::Result<Instruction>>,
+    ) -> io::Result<i64> {
+        let mut machine = Machine::default();
+        for instruction in instructions {
+            let instruction = instruction?;
+            (self.opcodes[instruction.opcode].apply)(&mut machine, &instruction.args);
+        }
+        Ok(machine.total)
+    }
+}
+
+struct Scanner<'a, R> {
+    registry: &'a Registry,
+    reader: R,
+    chunk: Vec<u8>,
+    pending: VecDeque<u8>,
+    read: usize,
+    candidate: Vec<u8>,
+    // Only kept once asked for, so that evaluating a stream stays in bounded memory.
+    near_misses: Option<Vec<NearMiss>>,
+}
+
+impl<'a, R: Read> Scanner<'a, R> {
+    fn new(registry: &'a Registry, reader: R, chunk_len: usize) -> Self {
+        Scanner {
+            registry,
+            reader,
+            chunk: vec![0; chunk_len],
+            pending: VecDeque::new(),
+            read: 0,
+            candidate: vec![],
+            near_misses: None,
+        }
+    }
+
+    fn record_near_misses(mut self) -> Self {
+        self.near_misses = Some(vec![]);
+        self
+    }
+
+    fn near_miss(&mut self, span: Range<usize>, rejection: Rejection) {
+        if let Some(near_misses) = &mut self.near_misses {
+            near_misses.push(NearMiss { span, rejection });
+        }
+    }
+
+    fn next_byte(&mut self) -> io::Result<Option<u8>> {
+        while self.pending.is_empty() {
+            match self.reader.read(&mut self.chunk) {
+                Ok(0) => return Ok(None),
+                Ok(n) => {
+                    self.pending.extend(&self.chunk[..n]);
+                    self.read += n;
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(self.pending.pop_front())
+    }
+}
+
+impl<R: Read> Iterator for Scanner<'_, R> {
+    type Item = io::Result<Instruction>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let byte = match self.next_byte() {
+                Ok(byte) => byte,
+                Err(err) => return Some(Err(err)),
+            };
+            let end = self.read - self.pending.len();
+            let span = end - self.candidate.len() - byte.is_some() as usize..end;
+            let Some(byte) = byte else {
+                if self.registry.heads_prefix_of(&self.candidate) {
+                    self.near_miss(span, Rejection::Truncated);
+                }
+                self.candidate.clear();
+                return None;
+            };
+            self.candidate.push(byte);
+            match self.registry.scan(&self.candidate) {
+                Ok(None) => {}
+                Ok(Some((opcode, args))) => {
+                    self.candidate.clear();
+                    return Some(Ok(Instruction { opcode, args, span }));
+                }
+                Err(rejection) => {
+                    if let Some(rejection) = rejection {
+                        self.near_miss(span, rejection);
+                    }
+                    // An instruction may start anywhere after the first byte of the rejected one.
+                    for &byte in self.candidate[1..].iter().rev() {
+                        self.pending.push_front(byte);
+                    }
+                    self.candidate.clear();
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_scanner() {
+    let registry = Registry::part2();
+    let mul = |a, b, span| Instruction {
+        opcode: 0,
+        args: vec![a, b],
+        span,
+    };
+    let op = |opcode, span| Instruction {
+        opcode,
+        args: vec![],
+        span,
+    };
+    for chunk_len in 1..=13 {
+        let mut scanner =
+            Scanner::new(&registry, TEST_INPUT.as_bytes(), chunk_len).record_near_misses();
+        assert_eq!(
+            scanner.by_ref().collect::<io::Result<Vec<_>>>().unwrap(),
+            vec![
+                mul(2, 4, 1..9),
+                op(2, 20..27),
+                mul(5, 5, 28..36),
+                mul(11, 8, 48..57),
+                op(1, 59..63),
+                mul(8, 5, 64..72)
+            ]
+        );
+        assert_eq!(
+            scanner.near_misses.unwrap(),
+            vec![
+                NearMiss {
+                    span: 10..14,
+                    rejection: Rejection::Unexpected(b'[')
+                },
+                NearMiss {
+                    span: 37..47,
+                    rejection: Rejection::Unexpected(b']')
+                },
+            ]
+        );
+    }
+    let mut scanner = Scanner::new(
+        &registry,
+        &b"mmul(1,mul(12,3)ddo()mul(1234,5)mul(1,23"[..],
+        3,
+    )
+    .record_near_misses();
+    assert_eq!(
+        scanner.by_ref().collect::<io::Result<Vec<_>>>().unwrap(),
+        vec![mul(12, 3, 7..16), op(1, 17..21)]
+    );
+    assert_eq!(
+        scanner
+            .near_misses
+            .unwrap()
+            .iter()
+            .map(|near_miss| (near_miss.span.clone(), near_miss.rejection))
+            .collect::<Vec<_>>(),
+        vec![
+            (1..8, Rejection::Unexpected(b'm')),
+            (21..29, Rejection::TooManyDigits),
+            (32..40, Rejection::Truncated)
+        ]
+    );
+    let mut scanner =
+        Scanner::new(&registry, &b"dog mule dont() mul(1,2) mu"[..], 5).record_near_misses();
+    assert_eq!(
+        scanner.by_ref().collect::<io::Result<Vec<_>>>().unwrap(),
+        vec![mul(1, 2, 16..24)]
+    );
+    assert_eq!(scanner.near_misses, Some(vec![]));
+    let mut scanner = Scanner::new(&registry, TEST_INPUT.as_bytes(), 4);
+    assert_eq!(scanner.by_ref().count(), 6);
+    assert_eq!(scanner.near_misses, None);
+}
+
+#[test]
+fn test_custom_registry() {
+    let registry = Registry::default()
+        .register("add", 2..=2, 3, |machine, args| {
+            machine.total += (args[0] + args[1]) as i64
+        })
+        .register("sub", 2..=2, 3, |machine, args| {
+            machine.total += args[0] as i64 - args[1] as i64
         })
-        .sum()
+        .register("mul", 1..=4, 2, |machine, args| {
+            if !machine.disabled {
+                machine.total += args.iter().product::<u64>() as i64
+            }
+        })
+        .register("toggle", 0..=0, 0, |machine, _| {
+            machine.disabled = !machine.disabled
+        });
+    let input =
+        "add(1,2)sub(1,20)mul(2,3,4)toggle()mul(5)toggle()mul(6)mul(100,1)mul(1,2,3,4,5)add()";
+    let instructions = Scanner::new(&registry, input.as_bytes(), 4);
+    assert_eq!(registry.evaluate(instructions).unwrap(), 3 - 19 + 24 + 6);
+}
+
+fn report(registry: &Registry, input: &str) -> String {
+    let line_starts = std::iter::once(0)
+        .chain(input.match_indices('\n').map(|(i, _)| i + 1))
+        .collect::<Vec<_>>();
+    let location = |offset: usize| {
+        let line = line_starts.partition_point(|&start| start <= offset);
+        format!("{line}:{} @{offset}", offset - line_starts[line - 1] + 1)
+    };
+    let mut scanner = Scanner::new(registry, input.as_bytes(), CHUNK_LEN).record_near_misses();
+    let mut machine = Machine::default();
+    let mut lines = vec![];
+    for instruction in scanner.by_ref() {
+        let instruction = instruction.unwrap();
+        let enabled = !machine.disabled;
+        let total = machine.total;
+        (registry.opcodes[instruction.opcode].apply)(&mut machine, &instruction.args);
+        lines.push((
+            instruction.span.start,
+            format!(
+                "{} {} args={:?} {} contribution={}",
+                location(instruction.span.start),
+                &input[instruction.span],
+                instruction.args,
+                if enabled { "enabled" } else { "disabled" },
+                machine.total - total
+            ),
+        ));
+    }
+    for near_miss in scanner.near_misses.unwrap() {
+        lines.push((
+            near_miss.span.start,
+            format!(
+                "{} {} rejected: {}",
+                location(near_miss.span.start),
+                String::from_utf8_lossy(&input.as_bytes()[near_miss.span]),
+                near_miss.rejection
+            ),
+        ));
+    }
+    lines.sort_by_key(|(offset, _)| *offset);
+    lines.push((0, format!("total={}", machine.total)));
+    lines.into_iter().map(|(_, line)| line + "\n").collect()
+}
+
+#[test]
+fn test_report() {
+    assert_eq!(
+        report(&Registry::part2(), TEST_INPUT),
+        "1:2 @1 mul(2,4) args=[2, 4] enabled contribution=8
+1:11 @10 mul[ rejected: unexpected '['
+1:21 @20 don't() args=[] enabled contribution=0
+1:29 @28 mul(5,5) args=[5, 5] disabled contribution=0
+1:38 @37 mul(32,64] rejected: unexpected ']'
+1:49 @48 mul(11,8) args=[11, 8] disabled contribution=0
+1:60 @59 do() args=[] disabled contribution=0
+1:65 @64 mul(8,5) args=[8, 5] enabled contribution=40
+total=48
+"
+    );
+    assert_eq!(
+        report(&Registry::part1(), "mul(1,2)\n  mul(3,4\n"),
+        "1:1 @0 mul(1,2) args=[1, 2] enabled contribution=2
+2:3 @11 mul(3,4\n rejected: unexpected '\\n'
+total=2
+"
+    );
+}
+
+fn process1(input: &str) -> i64 {
+    let input = &normalize(input);
+    let registry = Registry::part1();
+    let instructions = Scanner::new(&registry, input.as_bytes(), CHUNK_LEN);
+    registry.evaluate(instructions).unwrap()
 }
 
 #[test]
@@ -26,31 +462,11 @@ fn test_process1() {
     assert_eq!(process1(TEST_INPUT), 161)
 }
 
-fn process2(input: &str) -> u64 {
-    Regex::new(r"mul\(\d{1,3},\d{1,3}\)|don't\(\)|do\(\)")
-        .unwrap()
-        .find_iter(input)
-        .fold((0, true), |(sum, enabled), mat| {
-            let mat_str = mat.as_str();
-            if mat_str == "do()" {
-                (sum, true)
-            } else if mat_str == "don't()" {
-                (sum, false)
-            } else if enabled {
-                let (num1_str, num2_str) = mat_str
-                    .trim_start_matches("mul(")
-                    .trim_end_matches(")")
-                    .split_once(',')
-                    .unwrap();
-                (
-                    sum + num1_str.parse::<u64>().unwrap() * num2_str.parse::<u64>().unwrap(),
-                    true,
-                )
-            } else {
-                (sum, false)
-            }
-        })
-        .0
+fn process2(input: &str) -> i64 {
+    let input = &normalize(input);
+    let registry = Registry::part2();
+    let instructions = Scanner::new(&registry, input.as_bytes(), CHUNK_LEN);
+    registry.evaluate(instructions).unwrap()
 }
 
 #[test]
@@ -58,16 +474,32 @@ fn test_process2() {
     assert_eq!(process2(TEST_INPUT), 48)
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 161);
+        assert_eq!(process2(&input), 48);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
         .unwrap()
         .join(concat!("data/", env!("CARGO_PKG_NAME"), ".dat"));
-    let input = std::fs::read_to_string(path).unwrap();
+    let input = std::fs::read_to_string(&path).unwrap();
     let start = std::time::Instant::now();
     let result = process1(&input);
     println!("Result part 1: {result} in {:?}", start.elapsed());
     let start = std::time::Instant::now();
     let result = process2(&input);
     println!("Result part 2: {result} in {:?}", start.elapsed());
+    let start = std::time::Instant::now();
+    let registry = Registry::part2();
+    let instructions = Scanner::new(&registry, File::open(&path).unwrap(), CHUNK_LEN);
+    let result = registry.evaluate(instructions).unwrap();
+    println!("Result part 2 streamed: {result} in {:?}", start.elapsed());
+    if std::env::args().nth(1).as_deref() == Some("report") {
+        print!("{}", report(&Registry::part2(), &input));
+    }
 }
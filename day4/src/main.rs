@@ -1,6 +1,10 @@
-use std::path::PathBuf;
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+};
 
-use ndarray::Array2;
+use input::normalize;
+use ndarray::{s, Array2};
 
 #[cfg(test)]
 const TEST_INPUT: &str = "
@@ -49,7 +53,7 @@ fn test_parser() {
     );
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 enum Dir {
     Up,
     UpLeft,
@@ -74,14 +78,6 @@ impl Dir {
             Dir::UpRight,
         ]
     }
-    fn list_x() -> [[Dir; 2]; 4] {
-        [
-            [Dir::UpLeft, Dir::UpRight],
-            [Dir::DownLeft, Dir::DownRight],
-            [Dir::UpRight, Dir::DownRight],
-            [Dir::UpLeft, Dir::DownLeft],
-        ]
-    }
     fn next_ind(&self, index: [usize; 2]) -> Option<[usize; 2]> {
         Some(match self {
             Dir::Up => [index[0].checked_sub(1)?, index[1]],
@@ -108,35 +104,178 @@ impl Dir {
     }
 }
 
-fn check_next(
-    array: &Array2<char>,
-    ind: [usize; 2],
+struct Automaton {
+    goto: Vec<HashMap<char, usize>>,
+    fail: Vec<usize>,
+    out: Vec<Vec<usize>>,
+}
+
+impl Automaton {
+    fn new(words: &[&str]) -> Self {
+        let mut automaton = Automaton {
+            goto: vec![HashMap::new()],
+            fail: vec![0],
+            out: vec![vec![]],
+        };
+        // An empty word would be output by the root state, before any character.
+        for (i, word) in words
+            .iter()
+            .enumerate()
+            .filter(|(_, word)| !word.is_empty())
+        {
+            let mut state = 0;
+            for c in word.chars() {
+                state = match automaton.goto[state].get(&c) {
+                    Some(&next) => next,
+                    None => {
+                        automaton.goto.push(HashMap::new());
+                        automaton.fail.push(0);
+                        automaton.out.push(vec![]);
+                        let next = automaton.goto.len() - 1;
+                        automaton.goto[state].insert(c, next);
+                        next
+                    }
+                }
+            }
+            automaton.out[state].push(i);
+        }
+        let mut queue = automaton.goto[0].values().copied().collect::<VecDeque<_>>();
+        while let Some(state) = queue.pop_front() {
+            for (c, next) in automaton.goto[state].clone() {
+                let fail = automaton.next(automaton.fail[state], c);
+                automaton.fail[next] = fail;
+                let inherited = automaton.out[fail].clone();
+                automaton.out[next].extend(inherited);
+                queue.push_back(next);
+            }
+        }
+        automaton
+    }
+
+    fn next(&self, mut state: usize, c: char) -> usize {
+        loop {
+            if let Some(&next) = self.goto[state].get(&c) {
+                return next;
+            }
+            if state == 0 {
+                return 0;
+            }
+            state = self.fail[state];
+        }
+    }
+}
+
+// Each occurrence of a word is reported once: palindromes, which also read the same
+// from their other end, only going down, right or diagonally to the right, and
+// one-letter words only going right.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Hit<'a> {
+    start: [usize; 2],
     dir: Dir,
-    expected_char: char,
-) -> Option<[usize; 2]> {
-    let new_ind = dir.next_ind(ind)?;
-    let next_char = array.get(new_ind)?;
-    if next_char == &expected_char {
-        Some(new_ind)
-    } else {
-        None
+    word: &'a str,
+}
+
+fn search<'a>(array: &Array2<char>, words: &[&'a str]) -> Vec<Hit<'a>> {
+    let automaton = Automaton::new(words);
+    let lengths = words.iter().map(|w| w.chars().count()).collect::<Vec<_>>();
+    let in_bounds = |ind: [usize; 2]| array.get(ind).is_some();
+    let mut hits = vec![];
+    let mut line = vec![];
+    for dir in Dir::list() {
+        let line_starts = array
+            .indexed_iter()
+            .map(|((i, j), _)| [i, j])
+            .filter(|&ind| !dir.rev().next_ind(ind).is_some_and(in_bounds));
+        for line_start in line_starts {
+            line.clear();
+            let mut state = 0;
+            let mut ind = Some(line_start);
+            while let Some(cell) = ind.filter(|&ind| in_bounds(ind)) {
+                line.push(cell);
+                state = automaton.next(state, array[cell]);
+                for &word in automaton.out[state].iter() {
+                    let palindrome = words[word].chars().eq(words[word].chars().rev());
+                    let duplicate = match lengths[word] {
+                        1 => dir != Dir::Right,
+                        _ => palindrome && dir < dir.rev(),
+                    };
+                    if duplicate {
+                        continue;
+                    }
+                    hits.push(Hit {
+                        start: line[line.len() - lengths[word]],
+                        dir,
+                        word: words[word],
+                    });
+                }
+                ind = dir.next_ind(cell);
+            }
+        }
     }
+    hits
+}
+
+#[test]
+fn test_search() {
+    let array = parse("ABC\nDEF\nGHI");
+    let mut hits = search(&array, &["AB", "ABC", "BC", "IEA", "HEB", "F", "XYZ"]);
+    hits.sort();
+    assert_eq!(
+        hits,
+        vec![
+            Hit {
+                start: [0, 0],
+                dir: Dir::Right,
+                word: "AB"
+            },
+            Hit {
+                start: [0, 0],
+                dir: Dir::Right,
+                word: "ABC"
+            },
+            Hit {
+                start: [0, 1],
+                dir: Dir::Right,
+                word: "BC"
+            },
+            Hit {
+                start: [1, 2],
+                dir: Dir::Right,
+                word: "F"
+            },
+            Hit {
+                start: [2, 1],
+                dir: Dir::Up,
+                word: "HEB"
+            },
+            Hit {
+                start: [2, 2],
+                dir: Dir::UpLeft,
+                word: "IEA"
+            },
+        ]
+    );
+    let array = parse("ABA\nXBX\nABA");
+    let mut hits = search(&array, &["", "ABA", "BB"]);
+    hits.sort();
+    assert_eq!(
+        hits.iter()
+            .map(|hit| (hit.start, hit.dir, hit.word))
+            .collect::<Vec<_>>(),
+        [
+            ([0, 0], Dir::DownRight, "ABA"),
+            ([0, 0], Dir::Right, "ABA"),
+            ([0, 1], Dir::Down, "BB"),
+            ([1, 1], Dir::Down, "BB"),
+            ([2, 0], Dir::Right, "ABA"),
+            ([2, 0], Dir::UpRight, "ABA")
+        ]
+    );
 }
 
 fn process1(input: &str) -> usize {
-    let array = parse(input);
-    let n_lines = array.shape()[0];
-    let n_cols = array.shape()[1];
-    (0..n_lines)
-        .flat_map(|i| (0..n_cols).map(move |j| [i, j]))
-        .filter(|ind| array[*ind] == 'X')
-        .flat_map(|ind| Dir::list().into_iter().map(move |dir| (ind, dir)))
-        .filter_map(|(ind, dir)| {
-            check_next(&array, ind, dir, 'M')
-                .and_then(|ind| check_next(&array, ind, dir, 'A'))
-                .and_then(|ind| check_next(&array, ind, dir, 'S'))
-        })
-        .count()
+    let input = &normalize(input);
+    search(&parse(input), &["XMAS"]).len()
 }
 
 #[test]
@@ -144,21 +283,154 @@ fn test_process1() {
     assert_eq!(process1(TEST_INPUT), 18)
 }
 
-fn process2(input: &str) -> usize {
-    let array = parse(input);
-    let n_lines = array.shape()[0];
-    let n_cols = array.shape()[1];
-    (0..n_lines)
-        .flat_map(|i| (0..n_cols).map(move |j| [i, j]))
-        .filter(|ind| array[*ind] == 'A')
-        .flat_map(|ind| Dir::list_x().into_iter().map(move |dirs| (ind, dirs)))
-        .filter_map(|(ind, dirs)| {
-            check_next(&array, ind, dirs[0].rev(), 'M')
-                .and_then(|_| check_next(&array, ind, dirs[1].rev(), 'M'))
-                .and_then(|_| check_next(&array, ind, dirs[0], 'S'))
-                .and_then(|_| check_next(&array, ind, dirs[1], 'S'))
+#[derive(Debug, Clone, PartialEq)]
+struct Pattern {
+    cells: Array2<Option<char>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Symmetry {
+    Exact,
+    Rotations,
+    RotationsAndReflections,
+}
+
+#[derive(Debug, PartialEq)]
+struct Occurrence {
+    origin: [usize; 2],
+    variant: usize,
+}
+
+impl Pattern {
+    fn parse(template: &str) -> Self {
+        let rows = template
+            .split(['/', '\n'])
+            .map(str::trim)
+            .filter(|row| !row.is_empty())
+            .collect::<Vec<_>>();
+        let n_cols = rows[0].chars().count();
+        Pattern {
+            cells: Array2::from_shape_vec(
+                [rows.len(), n_cols],
+                rows.iter()
+                    .flat_map(|row| row.chars().map(|c| (c != '.').then_some(c)))
+                    .collect(),
+            )
+            .unwrap(),
+        }
+    }
+
+    fn rotate(&self) -> Self {
+        Pattern {
+            cells: self.cells.t().slice(s![.., ..;-1]).to_owned(),
+        }
+    }
+
+    fn mirror(&self) -> Self {
+        Pattern {
+            cells: self.cells.slice(s![.., ..;-1]).to_owned(),
+        }
+    }
+
+    fn variants(&self, symmetry: Symmetry) -> Vec<Pattern> {
+        let mut bases = vec![self.clone()];
+        if let Symmetry::RotationsAndReflections = symmetry {
+            bases.push(self.mirror());
+        }
+        let mut variants = Vec::<Pattern>::new();
+        for base in bases {
+            let mut pattern = base;
+            let n_rotations = if let Symmetry::Exact = symmetry { 1 } else { 4 };
+            for _ in 0..n_rotations {
+                let next = pattern.rotate();
+                if !variants.contains(&pattern) {
+                    variants.push(pattern);
+                }
+                pattern = next;
+            }
+        }
+        variants
+    }
+
+    // The whole template must lie on the grid, wildcards included.
+    fn matches_at(&self, array: &Array2<char>, [i, j]: [usize; 2]) -> bool {
+        let [rows, cols] = [self.cells.nrows(), self.cells.ncols()];
+        i + rows <= array.nrows()
+            && j + cols <= array.ncols()
+            && self
+                .cells
+                .indexed_iter()
+                .all(|((di, dj), cell)| cell.is_none_or(|c| array[[i + di, j + dj]] == c))
+    }
+}
+
+#[test]
+fn test_pattern_variants() {
+    let pattern = Pattern::parse("M.S/.A./M.S");
+    assert_eq!(pattern.rotate().rotate().rotate().rotate(), pattern);
+    assert_eq!(pattern.mirror(), Pattern::parse("S.M/.A./S.M"));
+    assert_eq!(pattern.rotate(), Pattern::parse("M.M/.A./S.S"));
+    assert_eq!(pattern.variants(Symmetry::Exact).len(), 1);
+    assert_eq!(pattern.variants(Symmetry::Rotations).len(), 4);
+    assert_eq!(pattern.variants(Symmetry::RotationsAndReflections).len(), 4);
+    let pattern = Pattern::parse("XM/.A");
+    assert_eq!(pattern.rotate(), Pattern::parse(".X/AM"));
+    assert_eq!(pattern.variants(Symmetry::RotationsAndReflections).len(), 8);
+}
+
+fn find_pattern(array: &Array2<char>, pattern: &Pattern, symmetry: Symmetry) -> Vec<Occurrence> {
+    let variants = pattern.variants(symmetry);
+    array
+        .indexed_iter()
+        .flat_map(|((i, j), _)| {
+            variants
+                .iter()
+                .enumerate()
+                .filter(move |(_, variant)| variant.matches_at(array, [i, j]))
+                .map(move |(variant, _)| Occurrence {
+                    origin: [i, j],
+                    variant,
+                })
         })
-        .count()
+        .collect()
+}
+
+#[test]
+fn test_find_pattern() {
+    let array = parse(TEST_INPUT);
+    let pattern = Pattern::parse("M.S/.A./M.S");
+    let occurrences = find_pattern(&array, &pattern, Symmetry::Exact);
+    assert_eq!(
+        occurrences.first(),
+        Some(&Occurrence {
+            origin: [0, 1],
+            variant: 0
+        })
+    );
+    assert_eq!(occurrences.len(), 2);
+    let pattern = Pattern::parse("XMAS");
+    assert_eq!(
+        find_pattern(&array, &pattern, Symmetry::RotationsAndReflections).len(),
+        search(&array, &["XMAS"])
+            .into_iter()
+            .filter(|hit| [Dir::Up, Dir::Left, Dir::Down, Dir::Right].contains(&hit.dir))
+            .count()
+    );
+    let pattern = Pattern::parse("B.");
+    assert!(find_pattern(&parse("AB"), &pattern, Symmetry::Exact).is_empty());
+    assert_eq!(
+        find_pattern(&parse("AB\nBA"), &pattern, Symmetry::Exact),
+        [Occurrence {
+            origin: [1, 0],
+            variant: 0
+        }]
+    );
+}
+
+fn process2(input: &str) -> usize {
+    let input = &normalize(input);
+    let pattern = Pattern::parse("M.S/.A./M.S");
+    find_pattern(&parse(input), &pattern, Symmetry::Rotations).len()
 }
 
 #[test]
@@ -166,6 +438,14 @@ fn test_process2() {
     assert_eq!(process2(TEST_INPUT), 9)
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 18);
+        assert_eq!(process2(&input), 9);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
@@ -178,4 +458,21 @@ fn main() {
     let start = std::time::Instant::now();
     let result = process2(&input);
     println!("Result part 2: {result} in {:?}", start.elapsed());
+    let mut args = std::env::args().skip(1);
+    if let Some(template) = args.next() {
+        let symmetry = match args.next().as_deref() {
+            Some("exact") => Symmetry::Exact,
+            Some("rotations") => Symmetry::Rotations,
+            _ => Symmetry::RotationsAndReflections,
+        };
+        let pattern = Pattern::parse(&template);
+        let occurrences = find_pattern(&parse(&normalize(&input)), &pattern, symmetry);
+        for occurrence in occurrences.iter() {
+            println!("{:?} variant {}", occurrence.origin, occurrence.variant);
+        }
+        println!(
+            "{} occurrences of {template} ({symmetry:?})",
+            occurrences.len()
+        );
+    }
 }
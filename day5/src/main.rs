@@ -1,8 +1,14 @@
 use std::{
-    collections::{HashMap, HashSet},
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+    error::Error,
+    fmt::Display,
     path::PathBuf,
 };
 
+use input::normalize;
+use itertools::Itertools;
+
 #[cfg(test)]
 const TEST_INPUT: &str = "
     47|53
@@ -60,6 +66,7 @@ fn parse(input: &str) -> (HashMap<usize, HashSet<usize>>, Vec<Vec<usize>>) {
 }
 
 fn process1(input: &str) -> usize {
+    let input = &normalize(input);
     let (rules, updates) = parse(input);
     updates
         .into_iter()
@@ -87,59 +94,389 @@ fn test_process1() {
     assert_eq!(process1(TEST_INPUT), 143)
 }
 
+#[derive(Debug, PartialEq)]
+struct CycleError {
+    pages: Vec<usize>,
+}
+
+impl Display for CycleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pages = self.pages.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        write!(
+            f,
+            "no valid order, rules form a cycle: {} -> {}",
+            pages.join(" -> "),
+            pages[0]
+        )
+    }
+}
+
+impl Error for CycleError {}
+
+fn reorder(
+    update: &[usize],
+    rules: &HashMap<usize, HashSet<usize>>,
+) -> Result<Vec<usize>, CycleError> {
+    let position = update
+        .iter()
+        .enumerate()
+        .map(|(i, page)| (*page, i))
+        .collect::<HashMap<_, _>>();
+    let successors = update
+        .iter()
+        .map(|page| {
+            rules
+                .get(page)
+                .into_iter()
+                .flatten()
+                .filter_map(|next| position.get(next).copied())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut in_degree = vec![0; update.len()];
+    for &next in successors.iter().flatten() {
+        in_degree[next] += 1;
+    }
+    let mut ready = (0..update.len())
+        .filter(|&i| in_degree[i] == 0)
+        .map(Reverse)
+        .collect::<BinaryHeap<_>>();
+    let mut ordered = Vec::with_capacity(update.len());
+    while let Some(Reverse(i)) = ready.pop() {
+        ordered.push(update[i]);
+        for &next in successors[i].iter() {
+            in_degree[next] -= 1;
+            if in_degree[next] == 0 {
+                ready.push(Reverse(next));
+            }
+        }
+    }
+    if ordered.len() == update.len() {
+        return Ok(ordered);
+    }
+    // Every page left over has a predecessor that is also left over, so walking
+    // predecessors from any of them must come back to a page already seen.
+    let predecessors = (0..update.len())
+        .filter(|&i| in_degree[i] > 0)
+        .flat_map(|i| successors[i].iter().map(move |&next| (next, i)))
+        .filter(|&(next, _)| in_degree[next] > 0)
+        .collect::<HashMap<_, _>>();
+    let mut path = vec![*predecessors.keys().min().unwrap()];
+    loop {
+        let prev = predecessors[path.last().unwrap()];
+        if let Some(start) = path.iter().position(|&i| i == prev) {
+            let mut pages = path[start..]
+                .iter()
+                .rev()
+                .map(|&i| update[i])
+                .collect::<Vec<_>>();
+            let first = (0..pages.len()).min_by_key(|&i| pages[i]).unwrap();
+            pages.rotate_left(first);
+            return Err(CycleError { pages });
+        }
+        path.push(prev);
+    }
+}
+
+#[test]
+fn test_reorder() {
+    let (rules, _) = parse(TEST_INPUT);
+    assert_eq!(
+        reorder(&[75, 47, 61, 53, 29], &rules),
+        Ok(vec![75, 47, 61, 53, 29])
+    );
+    assert_eq!(
+        reorder(&[75, 97, 47, 61, 53], &rules),
+        Ok(vec![97, 75, 47, 61, 53])
+    );
+    assert_eq!(reorder(&[61, 13, 29], &rules), Ok(vec![61, 29, 13]));
+    assert_eq!(
+        reorder(&[97, 13, 75, 29, 47], &rules),
+        Ok(vec![97, 75, 47, 29, 13])
+    );
+    let (rules, _) = parse("1|2\n2|3\n3|4\n4|2\n\n5,4,3,2,1");
+    let err = reorder(&[5, 4, 3, 2, 1], &rules).unwrap_err();
+    assert_eq!(err.pages, vec![2, 3, 4]);
+    assert_eq!(
+        err.to_string(),
+        "no valid order, rules form a cycle: 2 -> 3 -> 4 -> 2"
+    );
+}
+
+#[derive(Debug, PartialEq)]
+struct Violation {
+    rule: [usize; 2],
+    positions: [usize; 2],
+}
+
+#[derive(Debug, PartialEq)]
+struct Analysis {
+    violations: Vec<Vec<Violation>>,
+    unconstrained_pages: Vec<usize>,
+    // Rules implied by the others, left empty when the rules are cyclic.
+    redundant_rules: Vec<[usize; 2]>,
+    acyclic: bool,
+    total_order: Option<Vec<usize>>,
+}
+
+fn violations(update: &[usize], rules: &HashMap<usize, HashSet<usize>>) -> Vec<Violation> {
+    (0..update.len())
+        .flat_map(|i| (i + 1..update.len()).map(move |j| [i, j]))
+        .filter(|&[i, j]| {
+            rules
+                .get(&update[j])
+                .is_some_and(|set| set.contains(&update[i]))
+        })
+        .map(|[i, j]| Violation {
+            rule: [update[j], update[i]],
+            positions: [j, i],
+        })
+        .collect()
+}
+
+fn reaches(
+    rules: &HashMap<usize, HashSet<usize>>,
+    source: usize,
+    target: usize,
+    skipped: [usize; 2],
+) -> bool {
+    let mut visited = HashSet::from([source]);
+    let mut queue = VecDeque::from([source]);
+    while let Some(page) = queue.pop_front() {
+        for &next in rules.get(&page).into_iter().flatten() {
+            if [page, next] == skipped {
+                continue;
+            }
+            if next == target {
+                return true;
+            }
+            if visited.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    false
+}
+
+fn analyze(rules: &HashMap<usize, HashSet<usize>>, updates: &[Vec<usize>]) -> Analysis {
+    let mut pages = rules
+        .iter()
+        .flat_map(|(page, set)| set.iter().chain([page]))
+        .copied()
+        .collect::<Vec<_>>();
+    pages.sort();
+    pages.dedup();
+    let mut unconstrained_pages = updates
+        .iter()
+        .flatten()
+        .filter(|page| pages.binary_search(page).is_err())
+        .copied()
+        .collect::<Vec<_>>();
+    unconstrained_pages.sort();
+    unconstrained_pages.dedup();
+    // The rules define a total order exactly when they admit a single topological order.
+    let (acyclic, total_order) = match reorder(&pages, rules) {
+        Ok(order) => {
+            let total = order
+                .iter()
+                .tuple_windows()
+                .all(|(page, next)| rules.get(page).is_some_and(|set| set.contains(next)));
+            (true, total.then_some(order))
+        }
+        Err(_) => (false, None),
+    };
+    // Like the transitive reduction in `to_dot`, redundancy only means something without cycles.
+    let mut redundant_rules = if acyclic {
+        rules
+            .iter()
+            .flat_map(|(&page, set)| set.iter().map(move |&next| [page, next]))
+            .filter(|&[page, next]| reaches(rules, page, next, [page, next]))
+            .collect::<Vec<_>>()
+    } else {
+        vec![]
+    };
+    redundant_rules.sort();
+    Analysis {
+        violations: updates
+            .iter()
+            .map(|update| violations(update, rules))
+            .collect(),
+        unconstrained_pages,
+        redundant_rules,
+        acyclic,
+        total_order,
+    }
+}
+
+#[test]
+fn test_analyze() {
+    let (rules, updates) = parse(TEST_INPUT);
+    let analysis = analyze(&rules, &updates);
+    assert_eq!(
+        analysis.violations,
+        vec![
+            vec![],
+            vec![],
+            vec![],
+            vec![Violation {
+                rule: [97, 75],
+                positions: [1, 0]
+            }],
+            vec![Violation {
+                rule: [29, 13],
+                positions: [2, 1]
+            }],
+            vec![
+                Violation {
+                    rule: [75, 13],
+                    positions: [2, 1]
+                },
+                Violation {
+                    rule: [29, 13],
+                    positions: [3, 1]
+                },
+                Violation {
+                    rule: [47, 13],
+                    positions: [4, 1]
+                },
+                Violation {
+                    rule: [47, 29],
+                    positions: [4, 3]
+                },
+            ],
+        ]
+    );
+    assert!(analysis.unconstrained_pages.is_empty());
+    assert_eq!(analysis.redundant_rules.len(), 15);
+    assert!(!analysis.redundant_rules.contains(&[97, 75]));
+    assert!(analysis.redundant_rules.contains(&[97, 13]));
+    assert!(analysis.acyclic);
+    assert_eq!(analysis.total_order, Some(vec![97, 75, 47, 61, 53, 29, 13]));
+
+    let (rules, updates) = parse("1|2\n1|3\n\n3,1,2,4");
+    let analysis = analyze(&rules, &updates);
+    assert_eq!(analysis.unconstrained_pages, vec![4]);
+    assert!(analysis.redundant_rules.is_empty());
+    assert!(analysis.acyclic);
+    assert_eq!(analysis.total_order, None);
+
+    let (rules, updates) = parse("1|2\n2|3\n3|1\n1|3\n2|1\n\n1,2,3");
+    let analysis = analyze(&rules, &updates);
+    assert!(!analysis.acyclic);
+    assert!(analysis.redundant_rules.is_empty());
+}
+
+#[derive(Default)]
+struct DotOptions<'a> {
+    update: Option<&'a [usize]>,
+    only_update: bool,
+    reduce: bool,
+}
+
+fn to_dot(rules: &HashMap<usize, HashSet<usize>>, options: &DotOptions) -> String {
+    let update = options.update.unwrap_or_default();
+    let drawn = |page: &usize| !options.only_update || update.contains(page);
+    let rules = rules
+        .iter()
+        .filter(|(page, _)| drawn(page))
+        .map(|(&page, set)| (page, set.iter().copied().filter(drawn).collect()))
+        .collect::<HashMap<usize, HashSet<usize>>>();
+    let mut pages = rules
+        .iter()
+        .flat_map(|(page, set)| set.iter().chain([page]))
+        .chain(update.iter().filter(|page| drawn(page)))
+        .copied()
+        .collect::<Vec<_>>();
+    pages.sort();
+    pages.dedup();
+    let mut edges = rules
+        .iter()
+        .flat_map(|(&page, set)| set.iter().map(move |&next| [page, next]))
+        .collect::<Vec<_>>();
+    edges.sort();
+    let violated = violations(update, &rules)
+        .into_iter()
+        .map(|violation| violation.rule)
+        .collect::<HashSet<_>>();
+    // A transitive reduction only exists for acyclic rules, cyclic ones are drawn whole.
+    // Violated rules are kept even when implied so they stay visible.
+    if options.reduce && reorder(&pages, &rules).is_ok() {
+        edges.retain(|&[page, next]| {
+            violated.contains(&[page, next]) || !reaches(&rules, page, next, [page, next])
+        });
+    }
+    let mut dot = String::from("digraph rules {\n");
+    for page in pages {
+        if update.contains(&page) {
+            dot += &format!("    {page} [style=filled, fillcolor=lightblue];\n");
+        } else {
+            dot += &format!("    {page};\n");
+        }
+    }
+    for [page, next] in edges {
+        let attributes = if violated.contains(&[page, next]) {
+            " [color=red, penwidth=2]"
+        } else if update.contains(&page) && update.contains(&next) {
+            " [color=blue]"
+        } else {
+            ""
+        };
+        dot += &format!("    {page} -> {next}{attributes};\n");
+    }
+    dot + "}\n"
+}
+
+#[test]
+fn test_to_dot() {
+    let (rules, _) = parse("1|2\n2|3\n1|3\n3|4\n\n1,2");
+    assert_eq!(
+        to_dot(&rules, &DotOptions::default()),
+        "digraph rules {
+    1;
+    2;
+    3;
+    4;
+    1 -> 2;
+    1 -> 3;
+    2 -> 3;
+    3 -> 4;
+}
+"
+    );
+    let update = [3, 1, 2];
+    let options = DotOptions {
+        update: Some(&update),
+        only_update: true,
+        reduce: true,
+    };
+    assert_eq!(
+        to_dot(&rules, &options),
+        "digraph rules {
+    1 [style=filled, fillcolor=lightblue];
+    2 [style=filled, fillcolor=lightblue];
+    3 [style=filled, fillcolor=lightblue];
+    1 -> 2 [color=blue];
+    1 -> 3 [color=red, penwidth=2];
+    2 -> 3 [color=red, penwidth=2];
+}
+"
+    );
+    let options = DotOptions {
+        update: Some(&update),
+        ..DotOptions::default()
+    };
+    assert!(to_dot(&rules, &options).contains("    1 -> 3 [color=red, penwidth=2];\n"));
+    assert!(to_dot(&rules, &options).contains("    3 -> 4;\n"));
+}
+
 fn process2(input: &str) -> usize {
+    let input = &normalize(input);
     let (rules, updates) = parse(input);
     updates
         .into_iter()
-        .filter(|update| {
-            let mut prev_set = HashSet::<usize>::default();
-            for v in update.iter() {
-                if let Some(set) = rules.get(v) {
-                    if set.intersection(&prev_set).count() == 0 {
-                        prev_set.insert(*v);
-                    } else {
-                        return true;
-                    }
-                } else {
-                    prev_set.insert(*v);
-                }
-            }
-            false
-        })
-        .map(|update| {
-            let mut prev_set = HashSet::<usize>::default();
-            let mut new_updates = vec![];
-            for v in update.iter() {
-                if let Some(set) = rules.get(v) {
-                    let intersection = set
-                        .intersection(&prev_set)
-                        .copied()
-                        .collect::<HashSet<usize>>();
-                    if intersection.is_empty() {
-                        prev_set.insert(*v);
-                        new_updates.push(*v);
-                    } else {
-                        let i = intersection
-                            .iter()
-                            .map(|v| {
-                                new_updates
-                                    .iter()
-                                    .enumerate()
-                                    .find(|(_, vv)| v == *vv)
-                                    .unwrap()
-                                    .0
-                            })
-                            .min()
-                            .unwrap();
-                        new_updates.insert(i, *v);
-                        prev_set.insert(*v);
-                    }
-                } else {
-                    prev_set.insert(*v);
-                    new_updates.push(*v);
-                }
-            }
-            new_updates
+        .filter_map(|update| {
+            let ordered = reorder(&update, &rules).unwrap_or_else(|err| panic!("{err}"));
+            (ordered != update).then_some(ordered)
         })
         .map(|update| update[(update.len() - 1) / 2])
         .sum()
@@ -150,16 +487,53 @@ fn test_process2() {
     assert_eq!(process2(TEST_INPUT), 123)
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 143);
+        assert_eq!(process2(&input), 123);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
         .unwrap()
         .join(concat!("data/", env!("CARGO_PKG_NAME"), ".dat"));
     let input = std::fs::read_to_string(path).unwrap();
+    // `dot [reduce] [i]` only prints the rules in DOT, transitively reduced with `reduce`,
+    // and limited to the pages of the `i`th update when given.
+    let mut args = std::env::args().skip(1).peekable();
+    if args.next().as_deref() == Some("dot") {
+        let (rules, updates) = parse(&normalize(&input));
+        let reduce = args.next_if(|arg| arg == "reduce").is_some();
+        let update = args
+            .next()
+            .map(|i| updates[i.parse::<usize>().unwrap()].as_slice());
+        let options = DotOptions {
+            update,
+            only_update: update.is_some(),
+            reduce,
+        };
+        print!("{}", to_dot(&rules, &options));
+        return;
+    }
     let start = std::time::Instant::now();
     let result = process1(&input);
     println!("Result part 1: {result} in {:?}", start.elapsed());
     let start = std::time::Instant::now();
     let result = process2(&input);
     println!("Result part 2: {result} in {:?}", start.elapsed());
+    let start = std::time::Instant::now();
+    let (rules, updates) = parse(&normalize(&input));
+    let analysis = analyze(&rules, &updates);
+    println!(
+        "Analysis: {} updates with violations, unconstrained pages {:?}, {} redundant rules, acyclic: {}, total order: {:?} in {:?}",
+        analysis.violations.iter().filter(|v| !v.is_empty()).count(),
+        analysis.unconstrained_pages,
+        analysis.redundant_rules.len(),
+        analysis.acyclic,
+        analysis.total_order,
+        start.elapsed()
+    );
 }
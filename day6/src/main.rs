@@ -1,6 +1,11 @@
-use std::{collections::HashSet, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
 
+use input::normalize;
 use ndarray::Array2;
+use rayon::prelude::*;
 
 #[cfg(test)]
 const TEST_INPUT: &str = "
@@ -16,7 +21,13 @@ const TEST_INPUT: &str = "
     ......#...
 ";
 
-fn parse(input: &str) -> (Array2<bool>, [usize; 2]) {
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Guard {
+    pos: [usize; 2],
+    dir: Dir,
+}
+
+fn parse(input: &str) -> (Array2<bool>, Vec<Guard>) {
     let n_lines = input.trim().lines().count();
     let n_cols = input.trim().lines().next().unwrap().trim().len();
     let map = Array2::from_shape_vec(
@@ -28,22 +39,46 @@ fn parse(input: &str) -> (Array2<bool>, [usize; 2]) {
             .collect(),
     )
     .unwrap();
-    let pos = input
+    let guards = input
         .trim()
         .lines()
         .enumerate()
         .flat_map(|(i, line)| {
-            line.trim()
-                .chars()
-                .enumerate()
-                .find_map(|(j, c)| if c == '^' { Some([i, j]) } else { None })
+            line.trim().chars().enumerate().filter_map(move |(j, c)| {
+                let dir = match c {
+                    '^' => Dir::Up,
+                    '>' => Dir::Right,
+                    'v' => Dir::Down,
+                    '<' => Dir::Left,
+                    _ => return None,
+                };
+                Some(Guard { pos: [i, j], dir })
+            })
         })
-        .next()
-        .unwrap();
-    (map, pos)
+        .collect();
+    (map, guards)
 }
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+#[test]
+fn test_parse() {
+    let (map, guards) = parse(TEST_INPUT);
+    assert_eq!(map.shape(), [10, 10]);
+    assert_eq!(
+        guards,
+        [Guard {
+            pos: [6, 4],
+            dir: Dir::Up
+        }]
+    );
+    let (_, guards) = parse(".>.\n<#v\n");
+    assert_eq!(
+        guards.iter().map(|guard| guard.dir).collect::<Vec<_>>(),
+        [Dir::Right, Dir::Left, Dir::Down]
+    );
+    assert_eq!(guards[2].pos, [1, 2]);
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 enum Dir {
     Up,
     Down,
@@ -84,6 +119,14 @@ impl Dir {
             }
         }
     }
+    fn index(&self) -> usize {
+        match self {
+            Dir::Up => 0,
+            Dir::Down => 1,
+            Dir::Left => 2,
+            Dir::Right => 3,
+        }
+    }
     fn next_dir(&self) -> Self {
         match self {
             Dir::Up => Dir::Right,
@@ -92,23 +135,332 @@ impl Dir {
             Dir::Left => Dir::Up,
         }
     }
+    fn prev_dir(&self) -> Self {
+        match self {
+            Dir::Up => Dir::Left,
+            Dir::Left => Dir::Down,
+            Dir::Down => Dir::Right,
+            Dir::Right => Dir::Up,
+        }
+    }
+    fn opposite(&self) -> Self {
+        self.next_dir().next_dir()
+    }
 }
 
-fn process1(input: &str) -> usize {
-    let (map, mut pos) = parse(input);
-    let shape = map.shape();
-    let mut dir = Dir::Up;
-    let mut visited = HashSet::<[usize; 2]>::new();
-    visited.insert(pos);
-    while let Some(next_pos) = dir.next_pos(pos, shape) {
-        if map[next_pos] {
-            dir = dir.next_dir();
-        } else {
-            pos = next_pos;
-            visited.insert(pos);
+// How a guard changes heading in front of an obstacle.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Turn {
+    Right,
+    Left,
+    // Right on the first obstacle, left on the second and so on.
+    Alternating,
+    Reverse,
+}
+
+impl Turn {
+    fn list() -> [Turn; 4] {
+        [Turn::Right, Turn::Left, Turn::Alternating, Turn::Reverse]
+    }
+
+    fn apply(&self, dir: Dir, turns: usize) -> Dir {
+        match self {
+            Turn::Right => dir.next_dir(),
+            Turn::Left => dir.prev_dir(),
+            Turn::Alternating if turns.is_multiple_of(2) => dir.next_dir(),
+            Turn::Alternating => dir.prev_dir(),
+            Turn::Reverse => dir.opposite(),
+        }
+    }
+
+    // The part of the turn count that decides the next turn, which the guard state
+    // must include for a repeated state to mean a loop.
+    fn phase(&self, turns: usize) -> usize {
+        match self {
+            Turn::Alternating => turns % 2,
+            _ => 0,
+        }
+    }
+}
+
+// The guard walking from a given state, yielding its position and heading before
+// every move or turn. The iterator never ends when the guard is stuck in a loop.
+struct Patrol<'a> {
+    map: &'a Array2<bool>,
+    turn: Turn,
+    turns: usize,
+    state: Option<([usize; 2], Dir)>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Outcome {
+    // The last state on the map, from which the guard steps off it.
+    Exit { pos: [usize; 2], dir: Dir },
+    // `start` indexes the first state of the cycle in the route.
+    Loop { start: usize, length: usize },
+}
+
+struct Route {
+    states: Vec<([usize; 2], Dir)>,
+    outcome: Outcome,
+}
+
+impl<'a> Patrol<'a> {
+    fn new(map: &'a Array2<bool>, guard: Guard) -> Self {
+        Patrol {
+            map,
+            turn: Turn::Right,
+            turns: 0,
+            state: Some((guard.pos, guard.dir)),
+        }
+    }
+
+    fn with_turn(mut self, turn: Turn) -> Self {
+        self.turn = turn;
+        self
+    }
+
+    fn run(mut self) -> Route {
+        let mut seen = HashMap::<(([usize; 2], Dir), usize), usize>::new();
+        let mut states = vec![];
+        loop {
+            let phase = self.turn.phase(self.turns);
+            let Some(state) = self.next() else {
+                break;
+            };
+            if let Some(&start) = seen.get(&(state, phase)) {
+                let length = states.len() - start;
+                return Route {
+                    states,
+                    outcome: Outcome::Loop { start, length },
+                };
+            }
+            seen.insert((state, phase), states.len());
+            states.push(state);
+        }
+        let &(pos, dir) = states.last().unwrap();
+        Route {
+            states,
+            outcome: Outcome::Exit { pos, dir },
+        }
+    }
+}
+
+impl Iterator for Patrol<'_> {
+    type Item = ([usize; 2], Dir);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (pos, dir) = self.state?;
+        self.state = match dir.next_pos(pos, self.map.shape()) {
+            None => None,
+            Some(next_pos) if self.map[next_pos] => {
+                let dir = self.turn.apply(dir, self.turns);
+                self.turns += 1;
+                Some((pos, dir))
+            }
+            Some(next_pos) => Some((next_pos, dir)),
+        };
+        Some((pos, dir))
+    }
+}
+
+impl Route {
+    fn visited(&self) -> HashSet<[usize; 2]> {
+        self.states.iter().map(|&(pos, _)| pos).collect()
+    }
+
+    // Where the guard stands after `tick` moves or turns, `None` once it has left.
+    fn at(&self, tick: usize) -> Option<[usize; 2]> {
+        let index = match self.outcome {
+            Outcome::Loop { start, length } if tick >= self.states.len() => {
+                start + (tick - start) % length
+            }
+            _ => tick,
+        };
+        self.states.get(index).map(|&(pos, _)| pos)
+    }
+
+    fn cycle(&self) -> Option<&[([usize; 2], Dir)]> {
+        match self.outcome {
+            Outcome::Loop { start, .. } => Some(&self.states[start..]),
+            Outcome::Exit { .. } => None,
+        }
+    }
+}
+
+#[test]
+fn test_patrol() {
+    let (mut map, guards) = parse(TEST_INPUT);
+    let route = Patrol::new(&map, guards[0]).run();
+    assert_eq!(route.states[..2], [([6, 4], Dir::Up), ([5, 4], Dir::Up)]);
+    assert_eq!(route.states[6], ([1, 4], Dir::Right));
+    assert_eq!(
+        route.outcome,
+        Outcome::Exit {
+            pos: [9, 7],
+            dir: Dir::Down
+        }
+    );
+    assert_eq!(route.visited().len(), 41);
+    assert_eq!(route.at(route.states.len()), None);
+    map[[6, 3]] = true;
+    let route = Patrol::new(&map, guards[0]).run();
+    let Outcome::Loop { start, length } = route.outcome else {
+        panic!("expected a loop");
+    };
+    assert_eq!(route.states.len(), start + length);
+    let cycle = route.cycle().unwrap();
+    assert!(cycle.contains(&([6, 4], Dir::Left)));
+    let guard = Guard {
+        pos: cycle[0].0,
+        dir: cycle[0].1,
+    };
+    assert_eq!(Patrol::new(&map, guard).nth(length), Some(cycle[0]));
+    assert_eq!(route.at(start + 3 * length), Some(cycle[0].0));
+}
+
+#[test]
+fn test_turns() {
+    let (map, guards) = parse(TEST_INPUT);
+    let exit = |turn| Patrol::new(&map, guards[0]).with_turn(turn).run().outcome;
+    assert_eq!(
+        exit(Turn::Left),
+        Outcome::Exit {
+            pos: [1, 0],
+            dir: Dir::Left
+        }
+    );
+    assert_eq!(
+        exit(Turn::Reverse),
+        Outcome::Exit {
+            pos: [9, 4],
+            dir: Dir::Down
+        }
+    );
+    assert_eq!(
+        exit(Turn::Alternating),
+        Outcome::Exit {
+            pos: [0, 8],
+            dir: Dir::Up
+        }
+    );
+    // Reversing between two obstacles paces back and forth forever.
+    let (map, guards) = parse("#\n.\n^\n#\n");
+    let route = Patrol::new(&map, guards[0]).with_turn(Turn::Reverse).run();
+    assert_eq!(
+        route.outcome,
+        Outcome::Loop {
+            start: 0,
+            length: 4
         }
+    );
+}
+
+#[derive(Debug, PartialEq)]
+enum Encounter {
+    // The first time both guards stand on the same cell.
+    Collision {
+        guards: [usize; 2],
+        tick: usize,
+        pos: [usize; 2],
+    },
+    // Both guards end up walking the same cycle, whatever their offset along it.
+    SharedLoop {
+        guards: [usize; 2],
+    },
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
     }
-    visited.len()
+}
+
+fn same_cycle(a: &[([usize; 2], Dir)], b: &[([usize; 2], Dir)]) -> bool {
+    let Some(offset) = a.iter().position(|&state| state == b[0]) else {
+        return false;
+    };
+    a.len() == b.len()
+        && b.iter()
+            .enumerate()
+            .all(|(k, &state)| a[(offset + k) % a.len()] == state)
+}
+
+// Guards walk simultaneously, one move or turn per tick, and see straight through
+// each other.
+fn encounters(routes: &[Route]) -> Vec<Encounter> {
+    let mut encounters = vec![];
+    for (i, a) in routes.iter().enumerate() {
+        for (j, b) in routes.iter().enumerate().skip(i + 1) {
+            // Past this tick positions either repeat or one of the guards has left.
+            let horizon = match (a.outcome, b.outcome) {
+                (
+                    Outcome::Loop {
+                        start: start_a,
+                        length: length_a,
+                    },
+                    Outcome::Loop {
+                        start: start_b,
+                        length: length_b,
+                    },
+                ) => start_a.max(start_b) + length_a / gcd(length_a, length_b) * length_b,
+                (Outcome::Exit { .. }, Outcome::Loop { .. }) => a.states.len(),
+                (Outcome::Loop { .. }, Outcome::Exit { .. }) => b.states.len(),
+                (Outcome::Exit { .. }, Outcome::Exit { .. }) => a.states.len().min(b.states.len()),
+            };
+            if let Some((tick, pos)) = (0..horizon)
+                .map(|tick| (tick, a.at(tick)))
+                .find(|&(tick, pos)| pos.is_some() && pos == b.at(tick))
+            {
+                encounters.push(Encounter::Collision {
+                    guards: [i, j],
+                    tick,
+                    pos: pos.unwrap(),
+                });
+            }
+            if let (Some(cycle_a), Some(cycle_b)) = (a.cycle(), b.cycle()) {
+                if same_cycle(cycle_a, cycle_b) {
+                    encounters.push(Encounter::SharedLoop { guards: [i, j] });
+                }
+            }
+        }
+    }
+    encounters
+}
+
+#[test]
+fn test_encounters() {
+    let (map, guards) = parse(
+        "
+        .#.>.<
+        .^..#.
+        #.<...
+        ...#..
+        ",
+    );
+    let routes = guards
+        .iter()
+        .map(|&guard| Patrol::new(&map, guard).run())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        encounters(&routes),
+        [
+            Encounter::Collision {
+                guards: [0, 1],
+                tick: 1,
+                pos: [0, 4]
+            },
+            Encounter::SharedLoop { guards: [2, 3] }
+        ]
+    );
+}
+
+fn process1(input: &str) -> usize {
+    let input = &normalize(input);
+    let (map, guards) = parse(input);
+    Patrol::new(&map, guards[0]).run().visited().len()
 }
 
 #[test]
@@ -116,38 +468,130 @@ fn test_process1() {
     assert_eq!(process1(TEST_INPUT), 41)
 }
 
-fn process2(input: &str) -> usize {
-    let (map, init_pos) = parse(input);
-    let mut pos = init_pos;
-    let shape = map.shape();
-    let mut dir = Dir::Up;
-    let mut visited = HashSet::<[usize; 2]>::new();
-    while let Some(next_pos) = dir.next_pos(pos, shape) {
-        if map[next_pos] {
-            dir = dir.next_dir();
-        } else {
-            pos = next_pos;
-            visited.insert(pos);
-        }
-    }
-    let mut valid_block_pos_count = 0;
-    for block_pos in visited.into_iter() {
-        let mut visited = HashSet::<([usize; 2], Dir)>::default();
-        let mut dir = Dir::Up;
-        let mut pos = init_pos;
-        while let Some(next_pos) = dir.next_pos(pos, shape) {
-            if map[next_pos] || next_pos == block_pos {
-                dir = dir.next_dir();
+// For every cell and heading, the cell where the guard stops in front of the next
+// obstacle, or `None` when it walks off the map.
+struct Jumps {
+    stops: [Array2<Option<[usize; 2]>>; 4],
+}
+
+impl Jumps {
+    fn new(map: &Array2<bool>) -> Self {
+        let [n_rows, n_cols] = [map.shape()[0], map.shape()[1]];
+        let mut stops = [(); 4].map(|_| Array2::from_elem([n_rows, n_cols], None));
+        for i in 0..n_rows {
+            let mut stop = None;
+            for j in 0..n_cols {
+                if map[[i, j]] {
+                    stop = Some([i, j + 1]);
+                } else {
+                    stops[Dir::Left.index()][[i, j]] = stop;
+                }
+            }
+            let mut stop = None;
+            for j in (0..n_cols).rev() {
+                if map[[i, j]] {
+                    stop = j.checked_sub(1).map(|j| [i, j]);
+                } else {
+                    stops[Dir::Right.index()][[i, j]] = stop;
+                }
+            }
+        }
+        for j in 0..n_cols {
+            let mut stop = None;
+            for i in 0..n_rows {
+                if map[[i, j]] {
+                    stop = Some([i + 1, j]);
+                } else {
+                    stops[Dir::Up.index()][[i, j]] = stop;
+                }
+            }
+            let mut stop = None;
+            for i in (0..n_rows).rev() {
+                if map[[i, j]] {
+                    stop = i.checked_sub(1).map(|i| [i, j]);
+                } else {
+                    stops[Dir::Down.index()][[i, j]] = stop;
+                }
+            }
+        }
+        Jumps { stops }
+    }
+
+    fn next_stop(&self, pos: [usize; 2], dir: Dir, block: [usize; 2]) -> Option<[usize; 2]> {
+        let stop = self.stops[dir.index()][pos];
+        let (axis, forward) = match dir {
+            Dir::Up => (0, false),
+            Dir::Down => (0, true),
+            Dir::Left => (1, false),
+            Dir::Right => (1, true),
+        };
+        let other = 1 - axis;
+        let ahead = |cell: [usize; 2]| {
+            if forward {
+                cell[axis] > pos[axis]
             } else {
-                pos = next_pos;
-                if !visited.insert((pos, dir)) {
-                    valid_block_pos_count += 1;
-                    break;
-                };
+                cell[axis] < pos[axis]
             }
+        };
+        if block[other] != pos[other] || !ahead(block) {
+            return stop;
         }
+        // The obstacle in front of the stop is one step further than the stop itself.
+        let block_dist = block[axis].abs_diff(pos[axis]);
+        if stop.is_some_and(|stop| stop[axis].abs_diff(pos[axis]) < block_dist) {
+            return stop;
+        }
+        let mut before_block = block;
+        before_block[axis] = if forward {
+            block[axis] - 1
+        } else {
+            block[axis] + 1
+        };
+        Some(before_block)
     }
-    valid_block_pos_count
+
+    fn loops_with(&self, guard: Guard, block: [usize; 2]) -> bool {
+        let Guard { mut pos, mut dir } = guard;
+        let mut turns = HashSet::<([usize; 2], Dir)>::default();
+        while let Some(stop) = self.next_stop(pos, dir, block) {
+            if !turns.insert((stop, dir)) {
+                return true;
+            }
+            pos = stop;
+            dir = dir.next_dir();
+        }
+        false
+    }
+}
+
+#[test]
+fn test_jumps() {
+    let (map, guards) = parse(TEST_INPUT);
+    let init_pos = guards[0].pos;
+    let jumps = Jumps::new(&map);
+    assert_eq!(jumps.stops[Dir::Up.index()][init_pos], Some([1, 4]));
+    assert_eq!(jumps.stops[Dir::Right.index()][[1, 4]], Some([1, 8]));
+    assert_eq!(jumps.stops[Dir::Left.index()][[0, 3]], None);
+    let mut blocks = map
+        .indexed_iter()
+        .filter(|&(ind, &wall)| !wall && [ind.0, ind.1] != init_pos)
+        .map(|(ind, _)| [ind.0, ind.1])
+        .filter(|&block| jumps.loops_with(guards[0], block))
+        .collect::<Vec<_>>();
+    blocks.sort();
+    assert_eq!(blocks, [[6, 3], [7, 6], [7, 7], [8, 1], [8, 3], [9, 7]]);
+}
+
+fn process2(input: &str) -> usize {
+    let input = &normalize(input);
+    let (map, guards) = parse(input);
+    let mut visited = Patrol::new(&map, guards[0]).run().visited();
+    visited.remove(&guards[0].pos);
+    let jumps = Jumps::new(&map);
+    visited
+        .into_par_iter()
+        .filter(|&block_pos| jumps.loops_with(guards[0], block_pos))
+        .count()
 }
 
 #[test]
@@ -155,6 +599,14 @@ fn test_process2() {
     assert_eq!(process2(TEST_INPUT), 6)
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 41);
+        assert_eq!(process2(&input), 6);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
@@ -167,4 +619,21 @@ fn main() {
     let start = std::time::Instant::now();
     let result = process2(&input);
     println!("Result part 2: {result} in {:?}", start.elapsed());
+    let (map, guards) = parse(&normalize(&input));
+    for turn in Turn::list() {
+        let routes = guards
+            .iter()
+            .map(|&guard| Patrol::new(&map, guard).with_turn(turn).run())
+            .collect::<Vec<_>>();
+        for (i, route) in routes.iter().enumerate() {
+            println!(
+                "Guard {i} turning {turn:?}: {} states, {:?}",
+                route.states.len(),
+                route.outcome
+            );
+        }
+        for encounter in encounters(&routes) {
+            println!("Turning {turn:?}: {encounter:?}");
+        }
+    }
 }
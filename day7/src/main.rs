@@ -1,4 +1,8 @@
-use std::path::PathBuf;
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use input::normalize;
+use num_bigint::BigUint;
+use rayon::prelude::*;
 
 #[cfg(test)]
 const TEST_INPUT: &str = "
@@ -13,39 +17,188 @@ const TEST_INPUT: &str = "
     292: 11 6 16 20
 ";
 
-fn parse(input: &str) -> impl Iterator<Item = (u64, Vec<u64>)> + '_ {
+#[derive(Debug, PartialEq)]
+struct Overflow;
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "value does not fit the selected number type")
+    }
+}
+
+impl std::error::Error for Overflow {}
+
+// The arithmetic the backward search needs, with every operation checked so that a
+// number type too small for the input reports it instead of wrapping or saturating.
+trait Number: Clone + PartialEq + fmt::Display + FromStr + Send + Sync {
+    fn zero() -> Self;
+    fn checked_add(&self, rhs: &Self) -> Option<Self>;
+    fn checked_sub(&self, rhs: &Self) -> Option<Self>;
+    // `self / rhs` when `rhs` divides `self`.
+    fn exact_div(&self, rhs: &Self) -> Option<Self>;
+    // The number that `rhs` was concatenated to in order to give `self`.
+    fn strip_digits(&self, rhs: &Self) -> Option<Self>;
+}
+
+macro_rules! impl_number {
+    ($($t:ty),*) => {$(
+        impl Number for $t {
+            fn zero() -> Self {
+                0
+            }
+            fn checked_add(&self, rhs: &Self) -> Option<Self> {
+                <$t>::checked_add(*self, *rhs)
+            }
+            fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+                <$t>::checked_sub(*self, *rhs)
+            }
+            fn exact_div(&self, rhs: &Self) -> Option<Self> {
+                (*rhs != 0 && self.is_multiple_of(*rhs)).then(|| self / rhs)
+            }
+            fn strip_digits(&self, rhs: &Self) -> Option<Self> {
+                let shift = (10 as $t).checked_pow(rhs.checked_ilog10().unwrap_or(0) + 1)?;
+                (self % shift == *rhs).then(|| self / shift)
+            }
+        }
+    )*};
+}
+
+impl_number!(u64, u128);
+
+impl Number for BigUint {
+    fn zero() -> Self {
+        BigUint::ZERO
+    }
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        Some(self + rhs)
+    }
+    fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        (self >= rhs).then(|| self - rhs)
+    }
+    fn exact_div(&self, rhs: &Self) -> Option<Self> {
+        (*rhs != BigUint::ZERO && self % rhs == BigUint::ZERO).then(|| self / rhs)
+    }
+    fn strip_digits(&self, rhs: &Self) -> Option<Self> {
+        let shift = BigUint::from(10_u32).pow(rhs.to_str_radix(10).len() as u32);
+        (self % &shift == *rhs).then(|| self / shift)
+    }
+}
+
+fn parse_number<N: Number>(number: &str) -> Result<N, Overflow> {
+    assert!(
+        !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()),
+        "Invalid number {number}"
+    );
+    number.parse().map_err(|_| Overflow)
+}
+
+fn parse<N: Number>(input: &str) -> impl Iterator<Item = Result<(N, Vec<N>), Overflow>> + '_ {
     input.trim().lines().map(|line| {
         let (a, b) = line.trim().split_once(":").unwrap();
-        (
-            a.parse().unwrap(),
-            b.split_whitespace().map(|x| x.parse().unwrap()).collect(),
-        )
+        Ok((
+            parse_number(a)?,
+            b.split_whitespace()
+                .map(parse_number)
+                .collect::<Result<_, _>>()?,
+        ))
     })
 }
 
-fn process_single1(total: u64, curr_value: u64, mut rev_nexts: Vec<u64>) -> bool {
-    if curr_value > total {
+// The power of ten that shifts a number left by the digits of `n`.
+fn digit_shift(n: u64) -> Option<u64> {
+    10_u64.checked_pow(n.checked_ilog10().unwrap_or(0) + 1)
+}
+
+#[test]
+fn test_concat_digits() {
+    assert_eq!(concat(123, 456), Some(123456));
+    assert_eq!(concat(432, 1), Some(4321));
+    assert_eq!(concat(5, 0), Some(50));
+}
+
+// Whether `numbers` evaluate to `total` with `+`, `*` and optionally `||`, undoing the
+// operators from the last number backwards: `+` by subtraction, `*` only when the
+// total is divisible and `||` only when the total ends with the number's digits.
+fn reachable<N: Number>(total: &N, numbers: &[N], concat: bool) -> bool {
+    let Some((last, rest)) = numbers.split_last() else {
         return false;
+    };
+    if rest.is_empty() {
+        return total == last;
     }
-    if let Some(next) = rev_nexts.pop() {
-        process_single1(total, curr_value.saturating_add(next), rev_nexts.clone())
-            || process_single1(total, curr_value.saturating_mul(next), rev_nexts)
-    } else {
-        curr_value == total
-    }
+    total
+        .checked_sub(last)
+        .is_some_and(|total| reachable(&total, rest, concat))
+        || total
+            .exact_div(last)
+            .is_some_and(|total| reachable(&total, rest, concat))
+        || (concat
+            && total
+                .strip_digits(last)
+                .is_some_and(|total| reachable(&total, rest, concat)))
 }
 
-fn process1(input: &str) -> u64 {
-    parse(input)
-        .filter_map(|(total, mut nexts)| {
-            nexts.reverse();
-            if process_single1(total, nexts.pop().unwrap(), nexts) {
-                Some(total)
+#[test]
+fn test_reachable() {
+    assert!(reachable(&3267_u64, &[81, 40, 27], false));
+    assert!(!reachable(&156_u64, &[15, 6], false));
+    assert!(reachable(&156_u64, &[15, 6], true));
+    assert!(reachable(&7290_u64, &[6, 8, 6, 15], true));
+    assert!(!reachable(&21037_u64, &[9, 7, 18, 13], true));
+    let numbers = (0..60).map(|i| 11 + i * 7 % 30).collect::<Vec<u64>>();
+    let total = numbers.iter().sum::<u64>();
+    assert!(reachable(&total, &numbers, true));
+    assert!(!reachable(&(total * 1000 + 1), &numbers, true));
+}
+
+#[test]
+fn test_limits() {
+    let max = u64::MAX;
+    // Saturating arithmetic would turn both of these into `u64::MAX`.
+    assert!(!reachable(&max, &[max - 1, 2], false));
+    assert!(!reachable(&max, &[max / 2, 3], false));
+    assert!(reachable(&max, &[max - 1, 1], false));
+    assert!(reachable(&max, &[max / 10, 5], true));
+    assert!(reachable(&max, &[max / 5, 5], false));
+    assert!(!reachable(&max, &[max / 100, 16], true));
+    let big = u128::from(max) * 2;
+    assert!(reachable(&big, &[u128::from(max), 2], false));
+    assert!(reachable(&(big * 10 + 7), &[u128::from(max), 2, 7], true));
+    let lines = format!("{max}: {max}\n{max}: {max}\n");
+    assert_eq!(calibration::<u64>(&lines, false), Err(Overflow));
+    assert_eq!(calibration::<u128>(&lines, false), Ok(big));
+    let line = format!("{big}: {max} 2\n");
+    assert_eq!(calibration::<u64>(&line, false), Err(Overflow));
+    assert_eq!(calibration::<u128>(&line, false), Ok(big));
+    let huge = BigUint::from(u128::MAX) * 3_u32;
+    let line = format!("{huge}: {} 3\n", u128::MAX);
+    assert_eq!(calibration::<u128>(&line, false), Err(Overflow));
+    assert_eq!(calibration::<BigUint>(&line, false), Ok(huge.clone()));
+    let line = format!("{huge}3: {} 3 3\n", u128::MAX);
+    assert_eq!(
+        calibration::<BigUint>(&line, true),
+        Ok(huge * 10_u32 + 3_u32)
+    );
+}
+
+// The sum of the totals that `reachable` can produce.
+fn calibration<N: Number>(input: &str, concat: bool) -> Result<N, Overflow> {
+    parse::<N>(input)
+        .par_bridge()
+        .map(|line| {
+            let (total, numbers) = line?;
+            Ok(if reachable(&total, &numbers, concat) {
+                total
             } else {
-                None
-            }
+                N::zero()
+            })
         })
-        .sum()
+        .try_reduce(N::zero, |a, b| a.checked_add(&b).ok_or(Overflow))
+}
+
+fn process1(input: &str) -> u64 {
+    let input = &normalize(input);
+    calibration(input, false).unwrap()
 }
 
 #[test]
@@ -53,51 +206,230 @@ fn test_process1() {
     assert_eq!(process1(TEST_INPUT), 3749)
 }
 
-fn concat_digits(a: u64, b: u64) -> u64 {
-    let mut b_digits = 0;
-    let mut temp_b = b;
-    while temp_b > 0 {
-        b_digits += 1;
-        temp_b /= 10;
-    }
-    a * 10_u64.pow(b_digits) + b
+fn process2(input: &str) -> u64 {
+    let input = &normalize(input);
+    calibration(input, true).unwrap()
 }
 
 #[test]
-fn test_concat_digits() {
-    assert_eq!(concat_digits(123, 456), 123456);
-    assert_eq!(concat_digits(432, 1), 4321)
+fn test_process2() {
+    assert_eq!(process2(TEST_INPUT), 11387)
 }
 
-fn process_single2(total: u64, curr_value: u64, mut rev_nexts: Vec<u64>) -> bool {
-    if curr_value > total {
-        return false;
+fn concat(a: u64, b: u64) -> Option<u64> {
+    digit_shift(b)
+        .and_then(|shift| a.checked_mul(shift))
+        .and_then(|a| a.checked_add(b))
+}
+
+// An entry of the solver's operator table. `apply` returns `None` when the result is
+// negative, undefined or does not fit.
+#[derive(Clone, Copy)]
+struct Operator {
+    symbol: &'static str,
+    apply: fn(u64, u64) -> Option<u64>,
+    cost: u64,
+}
+
+impl Operator {
+    const ADD: Operator = Operator::new("+", u64::checked_add);
+    const MUL: Operator = Operator::new("*", u64::checked_mul);
+    const CONCAT: Operator = Operator::new("||", concat);
+    const SUB: Operator = Operator::new("-", u64::checked_sub);
+    const DIV: Operator = Operator::new("/", u64::checked_div);
+    const POW: Operator = Operator::new("^", |a, b| a.checked_pow(b.try_into().ok()?));
+    // Concatenation with the operands swapped: `12 <| 3` is `312`.
+    const REV_CONCAT: Operator = Operator::new("<|", |a, b| concat(b, a));
+
+    const fn new(symbol: &'static str, apply: fn(u64, u64) -> Option<u64>) -> Self {
+        Operator {
+            symbol,
+            apply,
+            cost: 1,
+        }
     }
-    if let Some(next) = rev_nexts.pop() {
-        process_single2(total, curr_value.saturating_add(next), rev_nexts.clone())
-            || process_single2(total, curr_value.saturating_mul(next), rev_nexts.clone())
-            || process_single2(total, concat_digits(curr_value, next), rev_nexts)
-    } else {
-        curr_value == total
+
+    fn with_cost(self, cost: u64) -> Self {
+        Operator { cost, ..self }
+    }
+
+    fn list() -> [Operator; 7] {
+        [
+            Operator::ADD,
+            Operator::MUL,
+            Operator::CONCAT,
+            Operator::SUB,
+            Operator::DIV,
+            Operator::POW,
+            Operator::REV_CONCAT,
+        ]
     }
 }
 
-fn process2(input: &str) -> u64 {
-    parse(input)
-        .filter_map(|(total, mut nexts)| {
-            nexts.reverse();
-            if process_single2(total, nexts.pop().unwrap(), nexts) {
-                Some(total)
-            } else {
-                None
+// Operators are evaluated left to right, without precedence.
+#[derive(Debug, PartialEq)]
+struct Equation {
+    total: u64,
+    numbers: Vec<u64>,
+    // The symbols of the operators between the numbers.
+    ops: Vec<&'static str>,
+    cost: u64,
+}
+
+impl fmt::Display for Equation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}", self.total, self.numbers[0])?;
+        for (op, number) in self.ops.iter().zip(&self.numbers[1..]) {
+            write!(f, " {op} {number}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Goal {
+    First,
+    All,
+    Cheapest,
+}
+
+struct Solver {
+    operators: Vec<Operator>,
+}
+
+impl Solver {
+    fn new(operators: &[Operator]) -> Self {
+        Solver {
+            operators: operators.to_vec(),
+        }
+    }
+
+    fn solve(&self, total: u64, numbers: &[u64]) -> Option<Equation> {
+        self.equations(total, numbers, Goal::First).pop()
+    }
+
+    fn solve_all(&self, total: u64, numbers: &[u64]) -> Vec<Equation> {
+        self.equations(total, numbers, Goal::All)
+    }
+
+    fn cheapest(&self, total: u64, numbers: &[u64]) -> Option<Equation> {
+        self.equations(total, numbers, Goal::Cheapest).pop()
+    }
+
+    fn equations(&self, total: u64, numbers: &[u64], goal: Goal) -> Vec<Equation> {
+        let mut search = Search {
+            total,
+            goal,
+            ops: vec![],
+            found: vec![],
+        };
+        self.search(&mut search, &numbers[1..], numbers[0], 0);
+        search
+            .found
+            .into_iter()
+            .map(|(ops, cost)| Equation {
+                total,
+                numbers: numbers.to_vec(),
+                ops,
+                cost,
+            })
+            .collect()
+    }
+
+    // Returns `true` once the search can stop.
+    fn search(&self, search: &mut Search, rest: &[u64], value: u64, cost: u64) -> bool {
+        if search.goal == Goal::Cheapest
+            && search.found.last().is_some_and(|&(_, best)| best <= cost)
+        {
+            return false;
+        }
+        let Some((&next, rest)) = rest.split_first() else {
+            if value != search.total {
+                return false;
             }
-        })
-        .sum()
+            if search.goal == Goal::Cheapest {
+                search.found.clear();
+            }
+            search.found.push((search.ops.clone(), cost));
+            return search.goal == Goal::First;
+        };
+        for op in &self.operators {
+            let Some(value) = (op.apply)(value, next) else {
+                continue;
+            };
+            search.ops.push(op.symbol);
+            if self.search(search, rest, value, cost + op.cost) {
+                return true;
+            }
+            search.ops.pop();
+        }
+        false
+    }
+}
+
+// For the cheapest goal `found` only keeps the best solution so far, and its cost
+// bounds the rest of the search.
+struct Search {
+    total: u64,
+    goal: Goal,
+    ops: Vec<&'static str>,
+    found: Vec<(Vec<&'static str>, u64)>,
 }
 
 #[test]
-fn test_process2() {
-    assert_eq!(process2(TEST_INPUT), 11387)
+fn test_solver() {
+    let solver = Solver::new(&[Operator::ADD, Operator::MUL]);
+    let equation = solver.solve(190, &[10, 19]).unwrap();
+    assert_eq!(equation.to_string(), "190 = 10 * 19");
+    let equations = solver.solve_all(3267, &[81, 40, 27]);
+    assert_eq!(
+        equations.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+        ["3267 = 81 + 40 * 27", "3267 = 81 * 40 + 27"]
+    );
+    assert_eq!(solver.solve(83, &[17, 5]), None);
+    let solver = Solver::new(&[Operator::ADD, Operator::MUL, Operator::CONCAT]);
+    let equation = solver.solve(7290, &[6, 8, 6, 15]).unwrap();
+    assert_eq!(equation.to_string(), "7290 = 6 * 8 || 6 * 15");
+    let solver = Solver::new(&[
+        Operator::ADD.with_cost(3),
+        Operator::MUL.with_cost(2),
+        Operator::CONCAT,
+        Operator::SUB,
+        Operator::DIV,
+        Operator::POW,
+        Operator::REV_CONCAT,
+    ]);
+    assert_eq!(solver.solve_all(4, &[2, 2]).len(), 3);
+    let equation = solver.cheapest(4, &[2, 2]).unwrap();
+    assert_eq!(
+        (equation.to_string(), equation.cost),
+        ("4 = 2 ^ 2".into(), 1)
+    );
+    assert_eq!(solver.solve(21, &[1, 2]).unwrap().ops, ["<|"]);
+    assert_eq!(solver.solve(5, &[11, 2]).unwrap().ops, ["/"]);
+    assert_eq!(solver.solve(3, &[2, 5, 4]).unwrap().ops, ["+", "-"]);
+    assert_eq!((Operator::SUB.apply)(2, 5), None);
+    assert_eq!((Operator::DIV.apply)(2, 0), None);
+    let solver = Solver::new(&[
+        Operator::new("max", |a, b| Some(a.max(b))),
+        Operator::new("%", u64::checked_rem).with_cost(2),
+    ]);
+    let equations = solver.solve_all(4, &[10, 6, 4]);
+    assert_eq!(
+        equations
+            .iter()
+            .map(|e| (e.to_string(), e.cost))
+            .collect::<Vec<_>>(),
+        [("4 = 10 % 6 max 4".into(), 3)]
+    );
+}
+
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 3749);
+        assert_eq!(process2(&input), 11387);
+    }
 }
 
 fn main() {
@@ -112,4 +444,52 @@ fn main() {
     let start = std::time::Instant::now();
     let result = process2(&input);
     println!("Result part 2: {result} in {:?}", start.elapsed());
+    let mut args = std::env::args().skip(1);
+    let command = args.next();
+    // `backend u128` or `backend big` redoes both parts with wider numbers.
+    if command.as_deref() == Some("backend") {
+        let input = normalize(&input);
+        let backend = args.next().unwrap_or_default();
+        for concat in [false, true] {
+            let result = match backend.as_str() {
+                "u128" => calibration::<u128>(&input, concat).map(|n| n.to_string()),
+                "big" => calibration::<BigUint>(&input, concat).map(|n| n.to_string()),
+                _ => calibration::<u64>(&input, concat).map(|n| n.to_string()),
+            };
+            match result {
+                Ok(result) => println!("Result with concatenation {concat}: {result}"),
+                Err(err) => println!("Result with concatenation {concat}: {err}"),
+            }
+        }
+    }
+    // `equations cheapest + * ||=5` prints the cheapest equation for every line with
+    // the given operators, each costing 1 unless a cost follows `=`. `first` and `all`
+    // print the first equation found and every equation instead.
+    if command.as_deref() == Some("equations") {
+        let goal = args.next().unwrap_or_default();
+        let mut ops = vec![];
+        for arg in args {
+            let (symbol, cost) = arg.split_once('=').unwrap_or((&arg, "1"));
+            let op = Operator::list()
+                .into_iter()
+                .find(|op| op.symbol == symbol)
+                .unwrap_or_else(|| panic!("Unknown operator {symbol}"));
+            ops.push(op.with_cost(cost.parse().unwrap()));
+        }
+        let solver = Solver::new(&ops);
+        for line in parse::<u64>(&normalize(&input)) {
+            let (total, numbers) = line.unwrap();
+            let equations = match goal.as_str() {
+                "first" => solver.solve(total, &numbers).into_iter().collect(),
+                "all" => solver.solve_all(total, &numbers),
+                _ => solver.cheapest(total, &numbers).into_iter().collect(),
+            };
+            if equations.is_empty() {
+                println!("{total} has no solution");
+            }
+            for equation in equations {
+                println!("{equation} (cost {})", equation.cost);
+            }
+        }
+    }
 }
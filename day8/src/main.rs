@@ -3,7 +3,8 @@ use std::{
     path::PathBuf,
 };
 
-use nalgebra::Vector2;
+use input::normalize;
+use nalgebra::{SVector, Vector2};
 
 #[cfg(test)]
 const TEST_INPUT: &str = "
@@ -21,7 +22,29 @@ const TEST_INPUT: &str = "
     ............
 ";
 
-fn parse(input: &str) -> ([i64; 2], HashMap<char, Vec<Vector2<i64>>>) {
+// The lattice points `p` with `min <= p < max` along every axis.
+#[derive(Debug, Clone, Copy)]
+struct Bounds<const N: usize> {
+    min: SVector<i64, N>,
+    max: SVector<i64, N>,
+}
+
+impl<const N: usize> Bounds<N> {
+    fn from_shape(shape: [i64; N]) -> Self {
+        Bounds {
+            min: SVector::zeros(),
+            max: SVector::from(shape),
+        }
+    }
+
+    fn contains(&self, pos: &SVector<i64, N>) -> bool {
+        pos.iter()
+            .zip(self.min.iter().zip(self.max.iter()))
+            .all(|(x, (min, max))| min <= x && x < max)
+    }
+}
+
+fn parse(input: &str) -> (Bounds<2>, HashMap<char, Vec<Vector2<i64>>>) {
     let nrows = input.trim().lines().count();
     let ncols = input.trim().lines().next().unwrap().trim().len();
     let mut map = HashMap::<char, Vec<Vector2<i64>>>::new();
@@ -34,42 +57,7 @@ fn parse(input: &str) -> ([i64; 2], HashMap<char, Vec<Vector2<i64>>>) {
             }
         }
     }
-    ([nrows as i64, ncols as i64], map)
-}
-
-fn process_single1(antena_pos: &[Vector2<i64>], shape: [i64; 2]) -> HashSet<Vector2<i64>> {
-    let mut antinodes = HashSet::new();
-    for (i, anten_i) in antena_pos.iter().enumerate() {
-        for anten_j in antena_pos.iter().skip(i + 1) {
-            let vec = anten_j - anten_i;
-            let anti_ij = anten_j + vec;
-            let anti_ji = anten_i - vec;
-            if anti_ij.iter().all(|&x| x >= 0) && anti_ij.iter().zip(&shape).all(|(&x, &y)| x < y) {
-                antinodes.insert(anti_ij);
-            }
-            if anti_ji.iter().all(|&x| x >= 0) && anti_ji.iter().zip(&shape).all(|(&x, &y)| x < y) {
-                antinodes.insert(anti_ji);
-            }
-        }
-    }
-    antinodes
-}
-
-fn process1(input: &str) -> usize {
-    let (shape, antena_map) = parse(input);
-    antena_map
-        .into_values()
-        .fold(HashSet::new(), |acc, antena_pos| {
-            acc.union(&process_single1(&antena_pos, shape))
-                .copied()
-                .collect()
-        })
-        .len()
-}
-
-#[test]
-fn test_process1() {
-    assert_eq!(process1(TEST_INPUT), 14)
+    (Bounds::from_shape([nrows as i64, ncols as i64]), map)
 }
 
 fn gcd(mut a: i64, mut b: i64) -> i64 {
@@ -89,48 +77,279 @@ fn test_gcd() {
     assert_eq!(gcd(0, 10), 10);
 }
 
-fn process_single2(antena_pos: &[Vector2<i64>], shape: [i64; 2]) -> HashSet<Vector2<i64>> {
-    let mut antinodes = HashSet::new();
-    for (i, anten_i) in antena_pos.iter().enumerate() {
-        for anten_j in antena_pos.iter().skip(i + 1) {
-            let mut vec = anten_j - anten_i;
-            vec /= gcd(vec.x, vec.y);
-            let mut anti_ij = *anten_j;
-            while anti_ij.iter().all(|&x| x >= 0)
-                && anti_ij.iter().zip(&shape).all(|(&x, &y)| x < y)
-            {
-                antinodes.insert(anti_ij);
-                anti_ij += vec;
+// The two modes are different rules, not one scale, so no `UpTo(k)` ever gives `All`:
+// `UpTo` steps whole antenna separations beyond a pair and never lands on the antennas
+// themselves (part 1), while `All` steps the separation reduced by its gcd and so also
+// takes the antennas and any lattice points between them (part 2).
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Harmonics {
+    // The points `k` separations beyond either antenna of a pair, for `1 <= k <= max`.
+    UpTo(usize),
+    // Every lattice point in line with a pair, the antennas included.
+    All,
+}
+
+#[derive(Debug, PartialEq)]
+struct Antinode<const N: usize> {
+    pos: SVector<i64, N>,
+    pair: [SVector<i64, N>; 2],
+}
+
+fn antinodes_of<const N: usize>(
+    antennas: &[SVector<i64, N>],
+    bounds: &Bounds<N>,
+    harmonics: Harmonics,
+) -> Vec<Antinode<N>> {
+    let mut antinodes = vec![];
+    for (i, &a) in antennas.iter().enumerate() {
+        for &b in antennas.iter().skip(i + 1) {
+            let pair = [a, b];
+            let mut walk = |start: SVector<i64, N>, step: SVector<i64, N>, max: usize| {
+                let mut pos = start;
+                for _ in 0..max {
+                    if !bounds.contains(&pos) {
+                        break;
+                    }
+                    antinodes.push(Antinode { pos, pair });
+                    pos += step;
+                }
+            };
+            // Antennas sharing a position have no line through them.
+            if a == b {
+                continue;
             }
-            let mut anti_ji = *anten_i;
-            while anti_ji.iter().all(|&x| x >= 0)
-                && anti_ji.iter().zip(&shape).all(|(&x, &y)| x < y)
-            {
-                antinodes.insert(anti_ji);
-                anti_ji -= vec;
+            let vec = b - a;
+            match harmonics {
+                Harmonics::UpTo(max) => {
+                    walk(b + vec, vec, max);
+                    walk(a - vec, -vec, max);
+                }
+                Harmonics::All => {
+                    let step = vec / vec.iter().fold(0, |acc, &x| gcd(acc, x));
+                    walk(a, step, usize::MAX);
+                    walk(a - step, -step, usize::MAX);
+                }
             }
         }
     }
     antinodes
 }
 
-fn process2(input: &str) -> usize {
-    let (shape, antena_map) = parse(input);
-    antena_map
-        .into_values()
-        .fold(HashSet::new(), |acc, antena_pos| {
-            acc.union(&process_single2(&antena_pos, shape))
-                .copied()
-                .collect()
-        })
+fn antinodes<const N: usize>(
+    antennas: &HashMap<char, Vec<SVector<i64, N>>>,
+    bounds: &Bounds<N>,
+    harmonics: Harmonics,
+) -> HashMap<char, Vec<Antinode<N>>> {
+    antennas
+        .iter()
+        .map(|(&freq, antennas)| (freq, antinodes_of(antennas, bounds, harmonics)))
+        .collect()
+}
+
+fn count_antinodes<const N: usize>(antinodes: &HashMap<char, Vec<Antinode<N>>>) -> usize {
+    antinodes
+        .values()
+        .flatten()
+        .map(|antinode| antinode.pos)
+        .collect::<HashSet<_>>()
         .len()
 }
 
+#[test]
+fn test_antinodes() {
+    let (bounds, antennas) = parse(TEST_INPUT);
+    let antinodes = antinodes(&antennas, &bounds, Harmonics::UpTo(1));
+    assert_eq!(antinodes[&'A'].len(), 5);
+    assert!(antinodes[&'A'].contains(&Antinode {
+        pos: Vector2::new(1, 3),
+        pair: [Vector2::new(5, 6), Vector2::new(9, 9)]
+    }));
+    let two = antinodes_of(&antennas[&'A'], &bounds, Harmonics::UpTo(2));
+    assert_eq!(two.len(), 7);
+    assert_eq!(
+        two.iter()
+            .filter(|antinode| antinode.pair == [Vector2::new(5, 6), Vector2::new(8, 8)])
+            .map(|antinode| antinode.pos)
+            .collect::<Vec<_>>(),
+        [Vector2::new(11, 10), Vector2::new(2, 4)]
+    );
+    let bounds = Bounds {
+        min: SVector::from([-1, -1, -1]),
+        max: SVector::from([3, 3, 3]),
+    };
+    let antennas = [SVector::from([0, 0, 0]), SVector::from([2, 2, 2])];
+    let all = antinodes_of(&antennas, &bounds, Harmonics::All);
+    assert_eq!(
+        all.iter()
+            .map(|antinode| antinode.pos[0])
+            .collect::<Vec<_>>(),
+        [0, 1, 2, -1]
+    );
+    assert_eq!(
+        antinodes_of(&antennas, &bounds, Harmonics::UpTo(1)).len(),
+        0
+    );
+    let antennas = [SVector::from([1, 1, 1]); 2];
+    assert!(antinodes_of(&antennas, &bounds, Harmonics::All).is_empty());
+    assert!(antinodes_of(&antennas, &bounds, Harmonics::UpTo(usize::MAX)).is_empty());
+}
+
+// The map with every antinode drawn as `#`, over any antenna on the same cell.
+fn render(
+    bounds: &Bounds<2>,
+    antennas: &HashMap<char, Vec<Vector2<i64>>>,
+    antinodes: &HashMap<char, Vec<Antinode<2>>>,
+) -> String {
+    let mut cells = HashMap::<Vector2<i64>, char>::new();
+    for (&freq, positions) in antennas {
+        for &pos in positions {
+            cells.insert(pos, freq);
+        }
+    }
+    for antinode in antinodes.values().flatten() {
+        cells.insert(antinode.pos, '#');
+    }
+    let mut map = String::new();
+    for i in bounds.min.x..bounds.max.x {
+        for j in bounds.min.y..bounds.max.y {
+            map.push(*cells.get(&Vector2::new(i, j)).unwrap_or(&'.'));
+        }
+        map.push('\n');
+    }
+    map
+}
+
+#[derive(Debug, PartialEq)]
+struct FrequencyStats {
+    freq: char,
+    antennas: usize,
+    antinodes: usize,
+    // Antinodes of this frequency that some other frequency also produces.
+    overlaps: usize,
+}
+
+fn stats<const N: usize>(
+    antennas: &HashMap<char, Vec<SVector<i64, N>>>,
+    antinodes: &HashMap<char, Vec<Antinode<N>>>,
+) -> Vec<FrequencyStats> {
+    let positions = antinodes
+        .iter()
+        .map(|(&freq, antinodes)| {
+            let positions = antinodes.iter().map(|antinode| antinode.pos);
+            (freq, positions.collect::<HashSet<_>>())
+        })
+        .collect::<HashMap<_, _>>();
+    let mut stats = positions
+        .iter()
+        .map(|(&freq, own)| FrequencyStats {
+            freq,
+            antennas: antennas[&freq].len(),
+            antinodes: own.len(),
+            overlaps: own
+                .iter()
+                .filter(|pos| {
+                    positions
+                        .iter()
+                        .any(|(&other, theirs)| other != freq && theirs.contains(pos))
+                })
+                .count(),
+        })
+        .collect::<Vec<_>>();
+    stats.sort_by_key(|stats| stats.freq);
+    stats
+}
+
+fn to_table(stats: &[FrequencyStats]) -> String {
+    let mut table = String::from("freq antennas antinodes overlaps\n");
+    for stats in stats {
+        table += &format!(
+            "{:>4} {:>8} {:>9} {:>8}\n",
+            stats.freq, stats.antennas, stats.antinodes, stats.overlaps
+        );
+    }
+    table
+}
+
+#[test]
+fn test_render() {
+    let (bounds, antennas) = parse(TEST_INPUT);
+    let antinodes = antinodes(&antennas, &bounds, Harmonics::UpTo(1));
+    assert_eq!(
+        render(&bounds, &antennas, &antinodes),
+        normalize(
+            "
+            ......#....#
+            ...#....0...
+            ....#0....#.
+            ..#....0....
+            ....0....#..
+            .#....#.....
+            ...#........
+            #......#....
+            ........A...
+            .........A..
+            ..........#.
+            ..........#.
+            "
+        )
+        .lines()
+        .map(|line| line.trim().to_string() + "\n")
+        .collect::<String>()
+    );
+    let stats = stats(&antennas, &antinodes);
+    assert_eq!(
+        stats,
+        [
+            FrequencyStats {
+                freq: '0',
+                antennas: 4,
+                antinodes: 10,
+                overlaps: 1
+            },
+            FrequencyStats {
+                freq: 'A',
+                antennas: 3,
+                antinodes: 5,
+                overlaps: 1
+            }
+        ]
+    );
+    assert_eq!(
+        to_table(&stats),
+        "freq antennas antinodes overlaps\n   0        4        10        1\n   A        3         5        1\n"
+    );
+}
+
+fn process1(input: &str) -> usize {
+    let input = &normalize(input);
+    let (bounds, antennas) = parse(input);
+    count_antinodes(&antinodes(&antennas, &bounds, Harmonics::UpTo(1)))
+}
+
+#[test]
+fn test_process1() {
+    assert_eq!(process1(TEST_INPUT), 14)
+}
+
+fn process2(input: &str) -> usize {
+    let input = &normalize(input);
+    let (bounds, antennas) = parse(input);
+    count_antinodes(&antinodes(&antennas, &bounds, Harmonics::All))
+}
+
 #[test]
 fn test_process2() {
     assert_eq!(process2(TEST_INPUT), 34)
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 14);
+        assert_eq!(process2(&input), 34);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
@@ -143,4 +362,36 @@ fn main() {
     let start = std::time::Instant::now();
     let result = process2(&input);
     println!("Result part 2: {result} in {:?}", start.elapsed());
+    // `pairs [k]` lists the antinodes of every frequency with the pair producing them,
+    // up to the `k`th harmonic (part 1 rule) or, without `k`, under the part 2 rule.
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("pairs") {
+        let harmonics = args
+            .next()
+            .map_or(Harmonics::All, |k| Harmonics::UpTo(k.parse().unwrap()));
+        let (bounds, antennas) = parse(&normalize(&input));
+        let antinodes = antinodes(&antennas, &bounds, harmonics);
+        let mut freqs = antinodes.keys().copied().collect::<Vec<_>>();
+        freqs.sort();
+        for freq in freqs {
+            for Antinode { pos, pair: [a, b] } in &antinodes[&freq] {
+                println!(
+                    "{freq} ({}, {}) ({}, {}) -> ({}, {})",
+                    a.x, a.y, b.x, b.y, pos.x, pos.y
+                );
+            }
+        }
+        println!("{} antinodes", count_antinodes(&antinodes));
+    }
+    // `render [k]` draws the antinodes on the map, then tabulates them per frequency.
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("render") {
+        let harmonics = args
+            .next()
+            .map_or(Harmonics::All, |k| Harmonics::UpTo(k.parse().unwrap()));
+        let (bounds, antennas) = parse(&normalize(&input));
+        let antinodes = antinodes(&antennas, &bounds, harmonics);
+        print!("{}", render(&bounds, &antennas, &antinodes));
+        print!("{}", to_table(&stats(&antennas, &antinodes)));
+    }
 }
@@ -1,9 +1,12 @@
 use std::{
     cmp::Reverse,
-    collections::{BTreeMap, BinaryHeap, HashMap},
+    collections::{BTreeMap, BTreeSet, BinaryHeap},
+    ops::Range,
     path::PathBuf,
 };
 
+use input::normalize;
+
 #[cfg(test)]
 const TEST_INPUT: &str = "2333133121414131402";
 
@@ -11,47 +14,567 @@ fn parse(input: &str) -> impl Iterator<Item = u64> + '_ {
     input.trim().chars().map(|c| c.to_digit(10).unwrap() as u64)
 }
 
-fn process1(input: &str) -> u64 {
-    let mut pos_length_files = BinaryHeap::new();
-    let mut pos_length_free = BinaryHeap::new();
-    let mut pos = 0;
-    let mut id = 0;
-    for (i, length) in parse(input).enumerate() {
-        if length == 0 {
-            continue;
-        }
-        if i % 2 == 0 {
-            pos_length_files.push((pos, length, id));
-            id += 1;
-        } else {
-            pos_length_free.push(Reverse((pos, length)));
-        }
-        pos += length;
-    }
-    while pos_length_files.peek().unwrap().0 > pos_length_free.peek().unwrap().0 .0 {
-        let Reverse((mut free_pos, mut free_length)) = pos_length_free.pop().unwrap();
-        let (file_pos, mut file_length, id) = pos_length_files.pop().unwrap();
-        match free_length.cmp(&file_length) {
-            std::cmp::Ordering::Less => {
-                file_length -= free_length;
-                pos_length_files.push((free_pos, free_length, id));
-                pos_length_files.push((file_pos, file_length, id));
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Extent {
+    pos: u64,
+    len: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Disk {
+    // The extents of each file in block order, indexed by file id.
+    files: Vec<Vec<Extent>>,
+    // `free[&len]` holds the positions of the free extents of `len` blocks. Lengths
+    // without any free extent have no entry.
+    free: BTreeMap<u64, BTreeSet<u64>>,
+    size: u64,
+}
+
+impl Disk {
+    fn parse(input: &str) -> Self {
+        let mut files = vec![];
+        let mut pos = 0;
+        for (i, len) in parse(input).enumerate() {
+            if i % 2 == 0 && len > 0 {
+                files.push(vec![Extent { pos, len }]);
+            }
+            pos += len;
+        }
+        let mut disk = Disk {
+            files,
+            free: Default::default(),
+            size: pos,
+        };
+        disk.reindex();
+        disk
+    }
+
+    fn add_free(&mut self, extent: Extent) {
+        self.free.entry(extent.len).or_default().insert(extent.pos);
+    }
+
+    fn remove_free(&mut self, extent: Extent) {
+        let positions = self.free.get_mut(&extent.len).unwrap();
+        positions.remove(&extent.pos);
+        if positions.is_empty() {
+            self.free.remove(&extent.len);
+        }
+    }
+
+    // Rebuilds the free-space index from the gaps between file extents.
+    fn reindex(&mut self) {
+        let mut extents = self.files.iter().flatten().copied().collect::<Vec<_>>();
+        extents.sort();
+        self.free = Default::default();
+        let mut pos = 0;
+        let end = Extent {
+            pos: self.size,
+            len: 0,
+        };
+        for extent in extents.into_iter().chain([end]) {
+            if extent.pos > pos {
+                self.add_free(Extent {
+                    pos,
+                    len: extent.pos - pos,
+                });
+            }
+            pos = extent.pos + extent.len;
+        }
+    }
+
+    fn checksum(&self) -> u64 {
+        self.files
+            .iter()
+            .enumerate()
+            .flat_map(|(id, extents)| {
+                extents.iter().map(move |extent| {
+                    (extent.pos..extent.pos + extent.len).sum::<u64>() * id as u64
+                })
+            })
+            .sum()
+    }
+
+    // The disk in the puzzle's notation, file ids past 9 wrapping to their last digit.
+    fn render(&self) -> String {
+        let mut blocks = vec!['.'; self.size as usize];
+        for (id, extents) in self.files.iter().enumerate() {
+            let digit = char::from_digit(id as u32 % 10, 10).unwrap();
+            for extent in extents {
+                blocks[extent.pos as usize..(extent.pos + extent.len) as usize].fill(digit);
+            }
+        }
+        blocks.into_iter().collect()
+    }
+
+    // Moves the blocks of `step.from` to `step.to`, merging extents of the file that end
+    // up next to each other. The free-space index is left for the caller to update.
+    fn apply(&mut self, step: &Step) {
+        let Step { id, from, to } = *step;
+        let extents = &mut self.files[id];
+        let i = extents
+            .iter()
+            .position(|e| e.pos <= from.pos && from.pos + from.len <= e.pos + e.len)
+            .expect("moved blocks belong to the file");
+        let extent = extents.remove(i);
+        let before = Extent {
+            pos: extent.pos,
+            len: from.pos - extent.pos,
+        };
+        let after = Extent {
+            pos: from.pos + from.len,
+            len: extent.pos + extent.len - from.pos - from.len,
+        };
+        let moved = Extent {
+            pos: to,
+            len: from.len,
+        };
+        extents.extend([before, after, moved].into_iter().filter(|e| e.len > 0));
+        extents.sort();
+        extents.dedup_by(|next, prev| {
+            let contiguous = prev.pos + prev.len == next.pos;
+            if contiguous {
+                prev.len += next.len;
             }
-            std::cmp::Ordering::Greater => {
-                pos_length_files.push((free_pos, file_length, id));
-                free_length -= file_length;
-                free_pos += file_length;
-                pos_length_free.push(Reverse((free_pos, free_length)));
+            contiguous
+        });
+    }
+
+    // Moves blocks one by one from the end of the disk into the leftmost free block,
+    // splitting files across extents.
+    fn block_steps(&mut self) -> BlockSteps<'_> {
+        let free = std::mem::take(&mut self.free)
+            .into_iter()
+            .flat_map(|(len, positions)| {
+                positions
+                    .into_iter()
+                    .map(move |pos| Reverse(Extent { pos, len }))
+            })
+            .collect();
+        let tail = self
+            .files
+            .iter()
+            .enumerate()
+            .flat_map(|(id, extents)| extents.iter().map(move |&extent| (extent, id)))
+            .collect();
+        BlockSteps {
+            disk: self,
+            free,
+            tail,
+        }
+    }
+
+    // Moves each whole file once, highest id first, into the free extent to its left
+    // that `fit` picks among those holding it.
+    fn file_steps(&mut self, fit: Fit) -> FileSteps<'_> {
+        let ids = 0..self.files.len();
+        FileSteps {
+            disk: self,
+            ids,
+            fit,
+        }
+    }
+
+    // Packs the extents, in disk order, against both ends of the disk so that the free
+    // space is left as a single extent. The split between the two ends is where the
+    // fewest blocks move, so extents after it move rightward. Files split by block
+    // compaction only come back together if their extents end up adjacent.
+    fn defrag_steps(&mut self) -> DefragSteps<'_> {
+        let mut extents = self
+            .files
+            .iter()
+            .enumerate()
+            .flat_map(|(id, extents)| extents.iter().map(move |&extent| (extent, id)))
+            .collect::<Vec<_>>();
+        extents.sort();
+        let mut left = Vec::with_capacity(extents.len());
+        let mut pos = 0;
+        for &(extent, _) in &extents {
+            left.push(pos);
+            pos += extent.len;
+        }
+        let mut right = vec![0; extents.len()];
+        let mut pos = self.size;
+        for (i, &(extent, _)) in extents.iter().enumerate().rev() {
+            pos -= extent.len;
+            right[i] = pos;
+        }
+        let cost = |i: usize, to: u64| {
+            let extent = extents[i].0;
+            if extent.pos == to {
+                0
+            } else {
+                extent.len
             }
-            std::cmp::Ordering::Equal => {
-                pos_length_files.push((free_pos, file_length, id));
+        };
+        let mut moved = (0..extents.len()).map(|i| cost(i, right[i])).sum::<u64>();
+        let mut split = (moved, 0);
+        for i in 0..extents.len() {
+            moved += cost(i, left[i]);
+            moved -= cost(i, right[i]);
+            split = split.min((moved, i + 1));
+        }
+        let split = split.1;
+        let steps = (0..split)
+            .map(|i| (i, left[i]))
+            .chain((split..extents.len()).rev().map(|i| (i, right[i])))
+            .filter(|&(i, to)| extents[i].0.pos != to)
+            .map(|(i, to)| Step {
+                id: extents[i].1,
+                from: extents[i].0,
+                to,
+            })
+            .collect::<Vec<_>>();
+        DefragSteps {
+            disk: self,
+            steps: steps.into_iter(),
+        }
+    }
+
+    fn compact(&mut self, strategy: Strategy) {
+        match strategy {
+            Strategy::Blocks => for _ in self.block_steps() {},
+            Strategy::Files(fit) => for _ in self.file_steps(fit) {},
+            Strategy::Defragment => for _ in self.defrag_steps() {},
+        }
+    }
+
+    fn fragmentation(&self) -> Fragmentation {
+        Fragmentation {
+            free_extents: self.free.values().map(|positions| positions.len()).sum(),
+            largest_free_extent: self.free.keys().next_back().copied().unwrap_or(0),
+            split_files: self
+                .files
+                .iter()
+                .filter(|extents| extents.len() > 1)
+                .count(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Fit {
+    // The leftmost free extent.
+    First,
+    // The shortest free extent, leftmost among equals.
+    Best,
+    // The longest free extent, leftmost among equals.
+    Worst,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Strategy {
+    Blocks,
+    Files(Fit),
+    Defragment,
+}
+
+impl Strategy {
+    fn list() -> [Strategy; 5] {
+        [
+            Strategy::Blocks,
+            Strategy::Files(Fit::First),
+            Strategy::Files(Fit::Best),
+            Strategy::Files(Fit::Worst),
+            Strategy::Defragment,
+        ]
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Fragmentation {
+    free_extents: usize,
+    largest_free_extent: u64,
+    split_files: usize,
+}
+
+// `from.len` blocks of file `id` moving to start at block `to`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Step {
+    id: usize,
+    from: Extent,
+    to: u64,
+}
+
+// Compaction runs while its steps are consumed and the free-space index is rebuilt
+// once the iterator is dropped, wherever it stopped.
+struct BlockSteps<'a> {
+    disk: &'a mut Disk,
+    free: BinaryHeap<Reverse<Extent>>,
+    tail: BinaryHeap<(Extent, usize)>,
+}
+
+impl BlockSteps<'_> {
+    fn disk(&self) -> &Disk {
+        self.disk
+    }
+}
+
+impl Iterator for BlockSteps<'_> {
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Step> {
+        let (&Reverse(mut hole), &(mut extent, id)) = (self.free.peek()?, self.tail.peek()?);
+        if extent.pos < hole.pos {
+            return None;
+        }
+        self.free.pop();
+        self.tail.pop();
+        extent.len -= 1;
+        let step = Step {
+            id,
+            from: Extent {
+                pos: extent.pos + extent.len,
+                len: 1,
+            },
+            to: hole.pos,
+        };
+        hole.pos += 1;
+        hole.len -= 1;
+        if hole.len > 0 {
+            self.free.push(Reverse(hole));
+        }
+        if extent.len > 0 {
+            self.tail.push((extent, id));
+        }
+        self.disk.apply(&step);
+        Some(step)
+    }
+}
+
+impl Drop for BlockSteps<'_> {
+    fn drop(&mut self) {
+        self.disk.reindex();
+    }
+}
+
+// Space freed behind a moved file stays out of the index until the iterator is
+// dropped, as every file still to move lies further left.
+struct FileSteps<'a> {
+    disk: &'a mut Disk,
+    ids: Range<usize>,
+    fit: Fit,
+}
+
+impl FileSteps<'_> {
+    fn disk(&self) -> &Disk {
+        self.disk
+    }
+}
+
+impl Iterator for FileSteps<'_> {
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Step> {
+        let disk = &mut *self.disk;
+        for id in self.ids.by_ref().rev() {
+            let [file] = disk.files[id][..] else {
+                continue;
+            };
+            // The leftmost free extent of each length holding the file, shortest first.
+            let mut holes = disk.free.range(file.len..).filter_map(|(&len, positions)| {
+                let pos = *positions.first()?;
+                (pos < file.pos).then_some(Extent { pos, len })
+            });
+            let hole = match self.fit {
+                Fit::First => holes.min_by_key(|hole| hole.pos),
+                Fit::Best => holes.next(),
+                Fit::Worst => holes.next_back(),
+            };
+            let Some(hole) = hole else {
+                continue;
+            };
+            disk.remove_free(hole);
+            if hole.len > file.len {
+                disk.add_free(Extent {
+                    pos: hole.pos + file.len,
+                    len: hole.len - file.len,
+                });
             }
+            let step = Step {
+                id,
+                from: file,
+                to: hole.pos,
+            };
+            disk.apply(&step);
+            return Some(step);
+        }
+        None
+    }
+}
+
+impl Drop for FileSteps<'_> {
+    fn drop(&mut self) {
+        self.disk.reindex();
+    }
+}
+
+struct DefragSteps<'a> {
+    disk: &'a mut Disk,
+    steps: std::vec::IntoIter<Step>,
+}
+
+impl DefragSteps<'_> {
+    fn disk(&self) -> &Disk {
+        self.disk
+    }
+}
+
+impl Iterator for DefragSteps<'_> {
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Step> {
+        let step = self.steps.next()?;
+        self.disk.apply(&step);
+        Some(step)
+    }
+}
+
+impl Drop for DefragSteps<'_> {
+    fn drop(&mut self) {
+        self.disk.reindex();
+    }
+}
+
+#[test]
+fn test_render() {
+    let mut disk = Disk::parse(TEST_INPUT);
+    assert_eq!(disk.render(), "00...111...2...333.44.5555.6666.777.888899");
+    let mut blocks = disk.clone();
+    let mut steps = blocks.block_steps();
+    let mut states = vec![];
+    while let Some(step) = steps.next() {
+        if states.is_empty() {
+            assert_eq!(
+                step,
+                Step {
+                    id: 9,
+                    from: Extent { pos: 41, len: 1 },
+                    to: 2
+                }
+            );
         }
+        states.push(steps.disk().render());
     }
-    pos_length_files
-        .into_iter()
-        .flat_map(|(pos, length, id)| (0..length).map(move |i| (pos + i) * id))
-        .sum()
+    assert_eq!(states.len(), 12);
+    assert_eq!(states[0], "009..111...2...333.44.5555.6666.777.88889.");
+    assert_eq!(states[11], "0099811188827773336446555566..............");
+    let mut replay = disk.clone();
+    let steps = disk.file_steps(Fit::First).collect::<Vec<_>>();
+    assert_eq!(steps.len(), 4);
+    assert_eq!(disk.render(), "00992111777.44.333....5555.6666.....8888..");
+    for step in &steps {
+        replay.apply(step);
+    }
+    assert_eq!(replay.render(), disk.render());
+    assert_eq!(steps[1].id, 7);
+    assert_eq!(steps[1].from, Extent { pos: 32, len: 3 });
+    assert_eq!(steps[1].to, 8);
+}
+
+#[test]
+fn test_disk() {
+    let disk = Disk::parse(TEST_INPUT);
+    assert_eq!(disk.size, 42);
+    assert_eq!(disk.files.len(), 10);
+    assert_eq!(disk.files[9], [Extent { pos: 40, len: 2 }]);
+    let lengths = disk
+        .free
+        .iter()
+        .map(|(&len, positions)| (len, positions.len()))
+        .collect::<Vec<_>>();
+    assert_eq!(lengths, [(1, 5), (3, 3)]);
+    assert_eq!(disk.free[&3].first(), Some(&2));
+    let mut blocks = disk.clone();
+    blocks.compact(Strategy::Blocks);
+    assert_eq!(blocks.files[9], [Extent { pos: 2, len: 2 }]);
+    assert_eq!(
+        blocks.files[8],
+        [Extent { pos: 4, len: 1 }, Extent { pos: 8, len: 3 }]
+    );
+    assert_eq!(
+        blocks
+            .free
+            .values()
+            .map(|positions| positions.len())
+            .sum::<usize>(),
+        1
+    );
+    let mut files = disk.clone();
+    files.compact(Strategy::Files(Fit::First));
+    assert_eq!(files.files[9], [Extent { pos: 2, len: 2 }]);
+    assert_eq!(files.files[8], [Extent { pos: 36, len: 4 }]);
+    assert_eq!(files.checksum(), 2858);
+}
+
+#[test]
+fn test_strategies() {
+    let disk = Disk::parse(TEST_INPUT);
+    let compacted = |strategy| {
+        let mut disk = disk.clone();
+        disk.compact(strategy);
+        disk
+    };
+    let blocks = compacted(Strategy::Blocks);
+    assert_eq!(
+        blocks.fragmentation(),
+        Fragmentation {
+            free_extents: 1,
+            largest_free_extent: 14,
+            split_files: 2
+        }
+    );
+    let first = compacted(Strategy::Files(Fit::First));
+    assert_eq!(
+        first.fragmentation(),
+        Fragmentation {
+            free_extents: 6,
+            largest_free_extent: 5,
+            split_files: 0
+        }
+    );
+    let fits = Disk::parse("1211131");
+    assert_eq!(fits.render(), "0..1.2...3");
+    let fitted = |fit| {
+        let mut disk = fits.clone();
+        disk.compact(Strategy::Files(fit));
+        disk.render()
+    };
+    assert_eq!(fitted(Fit::First), "0321......");
+    assert_eq!(fitted(Fit::Best), "021.3.....");
+    assert_eq!(fitted(Fit::Worst), "021...3...");
+    // Empty files leave free extents longer than any digit, of 12, 27 and 11 blocks.
+    let long = Disk::parse("190319090919023");
+    let first_step = |fit| long.clone().file_steps(fit).next().map(|step| step.to);
+    assert_eq!(first_step(Fit::First), Some(1));
+    assert_eq!(first_step(Fit::Best), Some(42));
+    assert_eq!(first_step(Fit::Worst), Some(14));
+    let mut defrag = disk.clone();
+    let steps = defrag.defrag_steps().collect::<Vec<_>>();
+    assert_eq!(
+        defrag.render(),
+        "00..............11123334455556666777888899"
+    );
+    assert_eq!(
+        defrag.fragmentation(),
+        Fragmentation {
+            free_extents: 1,
+            largest_free_extent: 14,
+            split_files: 0
+        }
+    );
+    assert!(steps.iter().all(|step| step.to > step.from.pos));
+    assert_eq!(steps.last().map(|step| step.id), Some(1));
+    assert_eq!(
+        defrag.checksum(),
+        compacted(Strategy::Defragment).checksum()
+    );
+}
+
+fn process1(input: &str) -> u64 {
+    let input = &normalize(input);
+    let mut disk = Disk::parse(input);
+    disk.compact(Strategy::Blocks);
+    disk.checksum()
 }
 
 #[test]
@@ -60,43 +583,10 @@ fn test_process1() {
 }
 
 fn process2(input: &str) -> u64 {
-    let mut pos_length_files = HashMap::<u64, (u64, u64)>::new();
-    let mut pos_length_free = BTreeMap::new();
-    let mut pos = 0;
-    let mut id = 0;
-    for (i, length) in parse(input).enumerate() {
-        if length == 0 {
-            continue;
-        }
-        if i % 2 == 0 {
-            pos_length_files.insert(id, (pos, length));
-            id += 1;
-        } else {
-            pos_length_free.insert(pos, length);
-        }
-        pos += length;
-    }
-    for id in (0..id).rev() {
-        let (pos_file, length_file) = pos_length_files.remove(&id).unwrap();
-        if let Some(pos_free) = pos_length_free
-            .iter()
-            .take_while(|(pos_free, _)| pos_free < &&pos_file)
-            .find(|(_, length_free)| length_free >= &&length_file)
-            .map(|(pos_free, _)| *pos_free)
-        {
-            let length_free = pos_length_free.remove(&pos_free).unwrap();
-            pos_length_files.insert(id, (pos_free, length_file));
-            if length_free > length_file {
-                pos_length_free.insert(pos_free + length_file, length_free - length_file);
-            }
-        } else {
-            pos_length_files.insert(id, (pos_file, length_file));
-        }
-    }
-    pos_length_files
-        .into_iter()
-        .flat_map(|(id, (pos, length))| (0..length).map(move |i| (pos + i) * id))
-        .sum()
+    let input = &normalize(input);
+    let mut disk = Disk::parse(input);
+    disk.compact(Strategy::Files(Fit::First));
+    disk.checksum()
 }
 
 #[test]
@@ -104,6 +594,14 @@ fn test_process2() {
     assert_eq!(process2(TEST_INPUT), 2858)
 }
 
+#[test]
+fn test_input_variants() {
+    for input in input::variants(TEST_INPUT) {
+        assert_eq!(process1(&input), 1928);
+        assert_eq!(process2(&input), 2858);
+    }
+}
+
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
@@ -116,4 +614,38 @@ fn main() {
     let start = std::time::Instant::now();
     let result = process2(&input);
     println!("Result part 2: {result} in {:?}", start.elapsed());
+    // `trace <disk map>` prints every intermediate state of both compactions.
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("trace") {
+        let mut disk = Disk::parse(&normalize(&args.next().unwrap()));
+        println!("{}", disk.render());
+        let mut blocks = disk.clone();
+        let mut steps = blocks.block_steps();
+        while let Some(step) = steps.next() {
+            println!("{} {step:?}", steps.disk().render());
+        }
+        println!();
+        println!("{}", disk.render());
+        let mut files = disk.clone();
+        let mut steps = files.file_steps(Fit::First);
+        while let Some(step) = steps.next() {
+            println!("{} {step:?}", steps.disk().render());
+        }
+        println!();
+        println!("{}", disk.render());
+        let mut steps = disk.defrag_steps();
+        while let Some(step) = steps.next() {
+            println!("{} {step:?}", steps.disk().render());
+        }
+    }
+    let disk = Disk::parse(&normalize(&input));
+    for strategy in Strategy::list() {
+        let mut disk = disk.clone();
+        disk.compact(strategy);
+        println!(
+            "{strategy:?}: checksum {}, {:?}",
+            disk.checksum(),
+            disk.fragmentation()
+        );
+    }
 }
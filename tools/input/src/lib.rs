@@ -0,0 +1,71 @@
+/// Brings a puzzle input to the shape the parsers expect: no byte order mark,
+/// `\n` line endings, tabs turned into spaces, no trailing whitespace on any
+/// line and no blank lines before or after the content.
+pub fn normalize(input: &str) -> String {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let input = input.replace("\r\n", "\n");
+    let lines = input
+        .split(['\n', '\r'])
+        .map(|line| line.replace('\t', " ").trim_end().to_string())
+        .collect::<Vec<_>>();
+    let Some(first) = lines.iter().position(|line| !line.is_empty()) else {
+        return String::new();
+    };
+    let last = lines.iter().rposition(|line| !line.is_empty()).unwrap();
+    lines[first..=last]
+        .iter()
+        .fold(String::with_capacity(input.len()), |mut acc, line| {
+            acc.push_str(line);
+            acc.push('\n');
+            acc
+        })
+}
+
+#[test]
+fn test_normalize() {
+    assert_eq!(
+        normalize("\u{feff}a  b\r\n\r\nc\td \n \n\n"),
+        "a  b\n\nc d\n"
+    );
+    assert_eq!(normalize("\n\n  x\ry\n"), "  x\ny\n");
+    assert_eq!(normalize(" \r\n\t"), "");
+}
+
+/// Rewrites a clean example input the way it could reach us from other
+/// editors or platforms, so each day can check its parsers survive
+/// [`normalize`].
+pub fn variants(input: &str) -> Vec<String> {
+    let tabbed = input
+        .lines()
+        .map(|line| {
+            let indent = if line.starts_with(' ') { "\t" } else { "" };
+            let words = line.split(' ').filter(|s| !s.is_empty());
+            format!("{indent}{}", words.collect::<Vec<_>>().join("\t"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    vec![
+        input.to_string(),
+        input.replace('\n', "\r\n"),
+        tabbed,
+        format!("\u{feff}{}", input.trim_start()),
+        input
+            .lines()
+            .map(|line| format!("{line} \t\n"))
+            .collect::<String>()
+            + "\n \n\n",
+    ]
+}
+
+#[test]
+fn test_variants() {
+    let words = |input: &str| {
+        input
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>()
+    };
+    for variant in variants("\n    3   4\n\n    5 6\n") {
+        assert_eq!(words(&normalize(&variant)), ["3 4", "", "5 6"]);
+    }
+}
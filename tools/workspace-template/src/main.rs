@@ -3,6 +3,7 @@ use std::fs;
 
 const TEMPLATE: &str = r#"use std::path::PathBuf;
 
+use input::normalize;
 
 #[cfg(test)]
 const TEST_INPUT: &str = "
@@ -15,6 +16,7 @@ fn parse(input: &str)  {
 }
 
 fn process1(input: &str) -> usize {
+    let input = &normalize(input);
     todo!()
 }
 
@@ -25,6 +27,7 @@ fn test_process1() {
 
 
 fn process2(input: &str) -> usize {
+    let input = &normalize(input);
     todo!()
 }
 
@@ -80,6 +83,7 @@ version = "0.1.0"
 edition = "2021"
 
 [dependencies]
+input = {{ path = "../tools/input" }}
 "#,
             day_name
         ),